@@ -0,0 +1,202 @@
+use crate::display::DisplayList;
+use crate::location::Location;
+
+/// How big the finished image is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CanvasSize {
+    /// A fixed size with the turtle's origin in the centre.
+    Fixed { width: u32, height: u32 },
+    /// Just big enough for the drawing plus a margin on every side.
+    Auto { margin: u32 },
+}
+
+/// The image a drawing is rendered onto. Drawings are kept in turtle coordinates and only
+/// placed on the image when it is rendered, so an auto sized canvas can grow to fit
+/// everything drawn.
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    size: CanvasSize,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Canvas {
+            size: CanvasSize::Fixed { width, height },
+        }
+    }
+
+    pub fn auto_sized(margin: u32) -> Self {
+        Canvas {
+            size: CanvasSize::Auto { margin },
+        }
+    }
+}
+
+impl Canvas {
+    /// Half the width and height of the canvas in turtle coordinates, or `None` when the
+    /// canvas grows to fit the drawing and so has no edges.
+    pub fn half_extents(&self) -> Option<(f64, f64)> {
+        match self.size {
+            CanvasSize::Fixed { width, height } => Some(((width / 2) as f64, (height / 2) as f64)),
+            CanvasSize::Auto { .. } => None,
+        }
+    }
+
+    /// Works out how big the image for `drawing` is and where the turtle's origin sits on it.
+    pub fn viewport(&self, drawing: &DisplayList) -> Viewport {
+        match self.size {
+            CanvasSize::Fixed { width, height } => Viewport {
+                width,
+                height,
+                origin: ((width / 2) as f64, (height / 2) as f64),
+            },
+            CanvasSize::Auto { margin } => {
+                let (min, max) = drawing
+                    .bounding_box()
+                    .unwrap_or((Location::origin(), Location::origin()));
+                Viewport {
+                    width: (max.x() - min.x()).ceil() as u32 + 2 * margin,
+                    height: (max.y() - min.y()).ceil() as u32 + 2 * margin,
+                    origin: (margin as f64 - min.x(), margin as f64 + max.y()),
+                }
+            }
+        }
+    }
+}
+
+/// A laid out canvas: its size in pixels and where turtle coordinates land on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+    origin: (f64, f64),
+}
+
+impl Viewport {
+    /// Where a point in turtle coordinates lands on the image. Turtle y points up, image y
+    /// points down.
+    pub fn to_image(&self, location: &Location) -> (f64, f64) {
+        (self.origin.0 + location.x(), self.origin.1 - location.y())
+    }
+
+    /// The bottom left and top right corners of the image in turtle coordinates.
+    pub fn area(&self) -> (Location, Location) {
+        (
+            Location::new(-self.origin.0, self.origin.1 - self.height as f64),
+            Location::new(self.width as f64 - self.origin.0, self.origin.1),
+        )
+    }
+
+    /// Clips a line in image coordinates to the edges of the image.
+    pub fn clip(&self, start: (f64, f64), end: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+        clip_line(start, end, self.width as f64, self.height as f64)
+    }
+}
+
+/// Clips a line to the rectangle from (0, 0) to (width, height) using Liang-Barsky.
+/// Returns `None` when none of the line is inside.
+pub fn clip_line(
+    start: (f64, f64),
+    end: (f64, f64),
+    width: f64,
+    height: f64,
+) -> Option<((f64, f64), (f64, f64))> {
+    let dx = end.0 - start.0;
+    let dy = end.1 - start.1;
+    let mut t0 = 0f64;
+    let mut t1 = 1f64;
+    for (p, q) in [
+        (-dx, start.0),
+        (dx, width - start.0),
+        (-dy, start.1),
+        (dy, height - start.1),
+    ] {
+        if p == 0f64 {
+            if q < 0f64 {
+                return None;
+            }
+        } else {
+            let r = q / p;
+            if p < 0f64 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    Some((
+        (start.0 + t0 * dx, start.1 + t0 * dy),
+        (start.0 + t1 * dx, start.1 + t1 * dy),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::fixtures::line;
+
+    #[test]
+    fn fixed_centres_origin() {
+        let viewport = Canvas::new(64, 32).viewport(&DisplayList::new());
+        assert_eq!((viewport.width, viewport.height), (64, 32));
+        assert_eq!(viewport.to_image(&Location::new(1.0, 2.0)), (33.0, 14.0));
+    }
+
+    #[test]
+    fn auto_size_fits_drawing() {
+        let mut drawing = DisplayList::new();
+        drawing.push(line((0.0, 0.0), (100.0, 0.0), 1));
+        drawing.push(line((100.0, 0.0), (100.0, -50.5), 1));
+        let viewport = Canvas::auto_sized(10).viewport(&drawing);
+        assert_eq!((viewport.width, viewport.height), (120, 71));
+        assert_eq!(viewport.to_image(&Location::origin()), (10.0, 10.0));
+        assert_eq!(
+            viewport.to_image(&Location::new(100.0, -50.5)),
+            (110.0, 60.5)
+        );
+    }
+
+    #[test]
+    fn auto_size_has_no_edges() {
+        let canvas = Canvas::auto_sized(10);
+        let viewport = canvas.viewport(&DisplayList::new());
+        assert_eq!(canvas.half_extents(), None);
+        assert_eq!((viewport.width, viewport.height), (20, 20));
+    }
+
+    #[test]
+    fn clip_inside_unchanged() {
+        let line = clip_line((1.0, 2.0), (3.0, 4.0), 10.0, 10.0);
+        assert_eq!(line, Some(((1.0, 2.0), (3.0, 4.0))));
+    }
+
+    #[test]
+    fn clip_crossing_edge() {
+        let line = clip_line((5.0, 5.0), (15.0, 5.0), 10.0, 10.0);
+        assert_eq!(line, Some(((5.0, 5.0), (10.0, 5.0))));
+    }
+
+    #[test]
+    fn clip_through_both_edges() {
+        let line = clip_line((-5.0, 5.0), (15.0, 5.0), 10.0, 10.0);
+        assert_eq!(line, Some(((0.0, 5.0), (10.0, 5.0))));
+    }
+
+    #[test]
+    fn clip_outside() {
+        let line = clip_line((-5.0, -5.0), (-1.0, 20.0), 10.0, 10.0);
+        assert_eq!(line, None);
+    }
+
+    #[test]
+    fn area_in_turtle_coordinates() {
+        let viewport = Canvas::new(64, 32).viewport(&DisplayList::new());
+        assert_eq!(
+            viewport.area(),
+            (Location::new(-32.0, -16.0), Location::new(32.0, 16.0))
+        );
+    }
+}
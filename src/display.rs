@@ -0,0 +1,683 @@
+use crate::location::Location;
+use crate::transform::Transform;
+use unsvg::{Color, COLORS};
+
+/// How a stroke combines with what is already drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ink {
+    /// Covers what is underneath in the pen colour.
+    Paint,
+    /// Paints the background colour back over what is underneath.
+    Erase,
+    /// Inverts the colours underneath.
+    Reverse,
+}
+
+/// A stroke broken into dashes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dash {
+    /// Lengths of dash and gap, alternating and starting with a dash.
+    pub pattern: Vec<f64>,
+    /// How far into the pattern the stroke starts, so dashes carry on across lines drawn
+    /// one after another.
+    pub offset: f64,
+}
+
+/// How something is stroked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub colour: Color,
+    pub width: f64,
+    pub ink: Ink,
+    /// Dashes to break the stroke into, or `None` for a solid stroke.
+    pub dash: Option<Dash>,
+}
+
+/// A single thing the turtle drew, in turtle coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand {
+    Line {
+        start: Location,
+        end: Location,
+        style: Style,
+    },
+    /// Part of a circle. `start` is the compass heading from the centre to where the arc
+    /// begins and `sweep` is how many degrees it runs clockwise (negative for anticlockwise).
+    Arc {
+        centre: Location,
+        radius: f64,
+        start: f64,
+        sweep: f64,
+        style: Style,
+    },
+    /// A filled polygon.
+    Fill {
+        points: Vec<Location>,
+        colour: Color,
+    },
+    Text {
+        position: Location,
+        text: String,
+        style: Style,
+    },
+}
+
+impl DrawCommand {
+    /// How far the turtle's pen travels drawing this command.
+    pub fn length(&self) -> f64 {
+        match self {
+            DrawCommand::Line { start, end, .. } => {
+                (end.x() - start.x()).hypot(end.y() - start.y())
+            }
+            DrawCommand::Arc { radius, sweep, .. } => radius * sweep.abs().min(360f64).to_radians(),
+            DrawCommand::Fill { .. } | DrawCommand::Text { .. } => 0f64,
+        }
+    }
+
+    /// The first `fraction` of this command, as if the turtle had stopped part way through
+    /// drawing it.
+    pub fn partial(&self, fraction: f64) -> DrawCommand {
+        match self {
+            DrawCommand::Line { start, end, style } => DrawCommand::Line {
+                start: start.clone(),
+                end: Location::new(
+                    start.x() + fraction * (end.x() - start.x()),
+                    start.y() + fraction * (end.y() - start.y()),
+                ),
+                style: style.clone(),
+            },
+            DrawCommand::Arc {
+                centre,
+                radius,
+                start,
+                sweep,
+                style,
+            } => DrawCommand::Arc {
+                centre: centre.clone(),
+                radius: *radius,
+                start: *start,
+                sweep: sweep * fraction,
+                style: style.clone(),
+            },
+            DrawCommand::Fill { .. } | DrawCommand::Text { .. } => self.clone(),
+        }
+    }
+
+    /// A triangle showing a turtle at `position` facing the compass `heading`.
+    pub fn turtle_sprite(position: &Location, heading: f64, colour: Color) -> DrawCommand {
+        let point = |angle: f64, distance: f64| {
+            let angle = (heading + angle).to_radians();
+            Location::new(
+                position.x() + distance * angle.sin(),
+                position.y() + distance * angle.cos(),
+            )
+        };
+        DrawCommand::Fill {
+            points: vec![
+                point(0f64, 10f64),
+                point(140f64, 8f64),
+                point(-140f64, 8f64),
+            ],
+            colour,
+        }
+    }
+
+    /// Points that together bound everything this command draws.
+    pub fn extent(&self) -> Vec<Location> {
+        match self {
+            DrawCommand::Line { start, end, .. } => vec![start.clone(), end.clone()],
+            DrawCommand::Arc {
+                centre,
+                radius,
+                start,
+                sweep,
+                ..
+            } => {
+                let (from, to) = if *sweep < 0f64 {
+                    (start + sweep, *start)
+                } else {
+                    (*start, start + sweep)
+                };
+                let point = |heading: f64| {
+                    let heading = heading.to_radians();
+                    Location::new(
+                        centre.x() + radius * heading.sin(),
+                        centre.y() + radius * heading.cos(),
+                    )
+                };
+                let mut points = vec![point(from), point(to)];
+                // The arc reaches furthest out wherever it passes a compass point.
+                let mut heading = (from / 90f64).ceil() * 90f64;
+                while heading < to {
+                    points.push(point(heading));
+                    heading += 90f64;
+                }
+                points
+            }
+            DrawCommand::Fill { points, .. } => points.clone(),
+            DrawCommand::Text { position, .. } => vec![position.clone()],
+        }
+    }
+}
+
+/// The palette colour numbered `colourcode`, if there is one.
+pub fn palette(colourcode: f32) -> Option<Color> {
+    (colourcode.fract() == 0f32 && (0f32..COLORS.len() as f32).contains(&colourcode))
+        .then(|| COLORS[colourcode as usize])
+}
+
+/// Where a turtle is and how it looks, for drawing it over the picture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    pub position: Location,
+    /// Compass degrees.
+    pub heading: f64,
+    pub colour: Color,
+    pub visible: bool,
+}
+
+impl Pose {
+    /// The turtle's triangle, if it is showing.
+    pub fn sprite(&self) -> Option<DrawCommand> {
+        self.visible
+            .then(|| DrawCommand::turtle_sprite(&self.position, self.heading, self.colour))
+    }
+}
+
+/// Everything a program drew, in the order it was drawn. Renderers turn this into an image
+/// after the program has finished.
+#[derive(Debug, Clone)]
+pub struct DisplayList {
+    commands: Vec<DrawCommand>,
+    background: Color,
+    /// How commands are placed on the canvas as they are pushed.
+    transform: Transform,
+    /// Transforms saved by `push_transform`, most recent last.
+    saved_transforms: Vec<Transform>,
+    /// Grid and axis lines painted under the drawing. The turtle didn't draw them, so
+    /// plotters leave them out and animations show them from the start.
+    guides: Vec<DrawCommand>,
+    /// Each turtle's pose on the canvas as the drawing went on, with how many commands
+    /// had been drawn by then and which turtle it was. Animations show the turtles from
+    /// these.
+    poses: Vec<(usize, usize, Pose)>,
+    /// Turtles painted over the drawing. Plotters leave them out.
+    sprites: Vec<DrawCommand>,
+}
+
+impl DisplayList {
+    pub fn new() -> Self {
+        DisplayList {
+            commands: Vec::new(),
+            background: COLORS[0],
+            transform: Transform::default(),
+            saved_transforms: Vec::new(),
+            guides: Vec::new(),
+            poses: Vec::new(),
+            sprites: Vec::new(),
+        }
+    }
+}
+
+impl Default for DisplayList {
+    fn default() -> Self {
+        DisplayList::new()
+    }
+}
+
+impl DisplayList {
+    /// Adds a command, placing it on the canvas with the current transform. This is the
+    /// only place turtle coordinates are scaled, rotated or moved.
+    pub fn push(&mut self, command: DrawCommand) {
+        self.commands.push(self.transform.command(command));
+    }
+
+    pub fn commands(&self) -> &[DrawCommand] {
+        &self.commands
+    }
+
+    /// Sets the guides painted under the drawing, already in canvas coordinates.
+    pub fn set_guides(&mut self, guides: Vec<DrawCommand>) {
+        self.guides = guides;
+    }
+
+    pub fn guides(&self) -> &[DrawCommand] {
+        &self.guides
+    }
+
+    /// The guides, the commands and then the turtles, in the order they are painted.
+    pub fn painted(&self) -> impl Iterator<Item = &DrawCommand> {
+        self.guides
+            .iter()
+            .chain(&self.commands)
+            .chain(&self.sprites)
+    }
+
+    /// Notes where the turtle numbered `turtle` is now, placing it on the canvas with the
+    /// current transform.
+    pub fn set_pose(&mut self, turtle: usize, pose: Pose) {
+        let pose = Pose {
+            position: self.transform.location(&pose.position),
+            heading: (pose.heading + self.transform.rotation).rem_euclid(360f64),
+            ..pose
+        };
+        self.poses.push((self.commands.len(), turtle, pose));
+    }
+
+    /// Each turtle's number and last pose from when `len` commands had been drawn.
+    /// Turtles that hadn't been noted by then aren't included.
+    pub fn poses(&self, len: usize) -> Vec<(usize, &Pose)> {
+        let mut poses: Vec<(usize, &Pose)> = Vec::new();
+        for (drawn, turtle, pose) in &self.poses {
+            if *drawn > len {
+                break;
+            }
+            match poses.iter_mut().find(|(other, _)| other == turtle) {
+                Some(last) => last.1 = pose,
+                None => poses.push((*turtle, pose)),
+            }
+        }
+        poses
+    }
+
+    /// The first pose noted once `len` commands had been drawn, which is the turtle that
+    /// drew the last of them.
+    pub fn pose_after(&self, len: usize) -> Option<(usize, &Pose)> {
+        self.poses
+            .iter()
+            .find(|(drawn, _, _)| *drawn == len)
+            .map(|(_, turtle, pose)| (*turtle, pose))
+    }
+
+    /// Sets the turtles painted over the drawing, already on the canvas.
+    pub fn set_sprites(&mut self, sprites: Vec<DrawCommand>) {
+        self.sprites = sprites;
+    }
+
+    /// Paints each turtle that is showing where it finished.
+    pub fn show_turtles(&mut self) {
+        self.sprites = self
+            .poses(self.commands.len())
+            .into_iter()
+            .filter_map(|(_, pose)| pose.sprite())
+            .collect();
+    }
+
+    pub fn sprites(&self) -> &[DrawCommand] {
+        &self.sprites
+    }
+
+    /// A copy of the first `len` commands without any turtles. They are already on the
+    /// canvas, so anything pushed onto the copy is placed as it is rather than transformed
+    /// again.
+    pub fn prefix(&self, len: usize) -> DisplayList {
+        DisplayList {
+            commands: self.commands[..len.min(self.commands.len())].to_vec(),
+            background: self.background,
+            transform: Transform::default(),
+            saved_transforms: Vec::new(),
+            guides: self.guides.clone(),
+            poses: Vec::new(),
+            sprites: Vec::new(),
+        }
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Scales everything drawn from now on about the turtle's origin.
+    pub fn set_scale(&mut self, scale: f64) -> Result<(), String> {
+        if !(scale.is_finite() && scale > 0f64) {
+            return Err(String::from("The scale must be a positive number"));
+        }
+        self.transform.scale = scale;
+        Ok(())
+    }
+
+    /// Turns everything drawn from now on clockwise about the turtle's origin.
+    pub fn set_rotation(&mut self, degrees: f64) -> Result<(), String> {
+        if !degrees.is_finite() {
+            return Err(String::from("The rotation must be a finite number"));
+        }
+        self.transform.rotation = degrees.rem_euclid(360f64);
+        Ok(())
+    }
+
+    /// Moves where the turtle's origin lands on the canvas for everything drawn from now on.
+    pub fn set_origin(&mut self, origin: Location) {
+        self.transform.origin = origin;
+    }
+
+    pub fn push_transform(&mut self) {
+        self.saved_transforms.push(self.transform.clone());
+    }
+
+    pub fn pop_transform(&mut self) -> Result<(), String> {
+        match self.saved_transforms.pop() {
+            Some(transform) => {
+                self.transform = transform;
+                Ok(())
+            }
+            None => Err(String::from("There is no saved transform to pop")),
+        }
+    }
+
+    /// The colour the canvas is filled with before anything is drawn.
+    pub fn background(&self) -> Color {
+        self.background
+    }
+
+    pub fn set_background(&mut self, colour: Color) {
+        self.background = colour;
+    }
+
+    /// Fills the whole canvas behind the drawing with a palette colour, as SETBACKGROUND
+    /// does.
+    pub fn set_background_colour(&mut self, colourcode: f32) -> Result<(), String> {
+        match palette(colourcode) {
+            Some(colour) => {
+                self.background = colour;
+                Ok(())
+            }
+            None => Err(format!(
+                "The background colour must be a whole number from 0 to {}",
+                COLORS.len() - 1
+            )),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// The smallest and largest corners of everything drawn, turtles included.
+    pub fn bounding_box(&self) -> Option<(Location, Location)> {
+        let mut points = self
+            .commands
+            .iter()
+            .chain(&self.sprites)
+            .flat_map(|command| command.extent());
+        let first = points.next()?;
+        let (mut min_x, mut min_y) = (first.x(), first.y());
+        let (mut max_x, mut max_y) = (first.x(), first.y());
+        for point in points {
+            min_x = min_x.min(point.x());
+            min_y = min_y.min(point.y());
+            max_x = max_x.max(point.x());
+            max_y = max_y.max(point.y());
+        }
+        Some((Location::new(min_x, min_y), Location::new(max_x, max_y)))
+    }
+}
+
+/// Strokes and lines for the tests of anything that draws.
+#[cfg(test)]
+pub mod fixtures {
+    use super::{DrawCommand, Ink, Style};
+    use crate::location::Location;
+    use unsvg::COLORS;
+
+    /// A solid stroke one unit wide in palette colour `colour`.
+    pub fn style(colour: usize) -> Style {
+        Style {
+            colour: COLORS[colour],
+            width: 1f64,
+            ink: Ink::Paint,
+            dash: None,
+        }
+    }
+
+    /// A line from `start` to `end` stroked in `style(colour)`.
+    pub fn line(start: (f64, f64), end: (f64, f64), colour: usize) -> DrawCommand {
+        DrawCommand::Line {
+            start: Location::new(start.0, start.1),
+            end: Location::new(end.0, end.1),
+            style: style(colour),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::style;
+    use super::*;
+
+    #[test]
+    fn empty_has_no_bounding_box() {
+        let list = DisplayList::new();
+        assert!(list.is_empty());
+        assert_eq!(list.bounding_box(), None);
+    }
+
+    #[test]
+    fn bounding_box_of_lines() {
+        let mut list = DisplayList::new();
+        list.push(DrawCommand::Line {
+            start: Location::new(-5.0, 2.0),
+            end: Location::new(3.0, 7.0),
+            style: style(1),
+        });
+        list.push(DrawCommand::Text {
+            position: Location::new(10.0, -1.0),
+            text: String::from("hi"),
+            style: style(1),
+        });
+        assert_eq!(
+            list.bounding_box(),
+            Some((Location::new(-5.0, -1.0), Location::new(10.0, 7.0)))
+        );
+    }
+
+    #[test]
+    fn arc_extent_includes_compass_points() {
+        let arc = DrawCommand::Arc {
+            centre: Location::origin(),
+            radius: 10.0,
+            start: 45.0,
+            sweep: 90.0,
+            style: style(1),
+        };
+        let points = arc.extent();
+        assert_eq!(points.len(), 3);
+        assert!(points
+            .iter()
+            .any(|point| (point.x() - 10.0).abs() < 1e-9 && point.y().abs() < 1e-9));
+    }
+
+    #[test]
+    fn anticlockwise_arc_extent() {
+        let arc = DrawCommand::Arc {
+            centre: Location::origin(),
+            radius: 10.0,
+            start: 0.0,
+            sweep: -180.0,
+            style: style(1),
+        };
+        let points = arc.extent();
+        assert!(points
+            .iter()
+            .any(|point| (point.x() + 10.0).abs() < 1e-9 && point.y().abs() < 1e-9));
+    }
+
+    #[test]
+    fn command_lengths() {
+        let line = DrawCommand::Line {
+            start: Location::new(0.0, 0.0),
+            end: Location::new(3.0, 4.0),
+            style: style(1),
+        };
+        let arc = DrawCommand::Arc {
+            centre: Location::origin(),
+            radius: 2.0,
+            start: 0.0,
+            sweep: -180.0,
+            style: style(1),
+        };
+        assert_eq!(line.length(), 5.0);
+        assert_eq!(arc.length(), 2.0 * std::f64::consts::PI);
+    }
+
+    #[test]
+    fn partial_line() {
+        let line = DrawCommand::Line {
+            start: Location::new(0.0, 0.0),
+            end: Location::new(10.0, 20.0),
+            style: style(1),
+        };
+        assert_eq!(
+            line.partial(0.5),
+            DrawCommand::Line {
+                start: Location::new(0.0, 0.0),
+                end: Location::new(5.0, 10.0),
+                style: style(1),
+            }
+        );
+    }
+
+    #[test]
+    fn prefix_of_list() {
+        let mut list = DisplayList::new();
+        for _ in 0..3 {
+            list.push(DrawCommand::Text {
+                position: Location::origin(),
+                text: String::from("hi"),
+                style: style(1),
+            });
+        }
+        assert_eq!(list.prefix(2).commands().len(), 2);
+        assert_eq!(list.prefix(10).commands().len(), 3);
+    }
+
+    #[test]
+    fn sprite_points_along_heading() {
+        let sprite = DrawCommand::turtle_sprite(&Location::origin(), 90.0, COLORS[1]);
+        match sprite {
+            DrawCommand::Fill { points, .. } => {
+                assert!((points[0].x() - 10.0).abs() < 1e-9);
+                assert!(points[0].y().abs() < 1e-9);
+            }
+            _ => panic!("expected a fill"),
+        }
+    }
+
+    #[test]
+    fn prefix_keeps_background() {
+        let mut list = DisplayList::new();
+        assert_eq!(list.background(), COLORS[0]);
+        list.set_background(COLORS[3]);
+        assert_eq!(list.prefix(0).background(), COLORS[3]);
+    }
+
+    #[test]
+    fn guides_are_painted_first() {
+        let mut list = DisplayList::new();
+        list.push(DrawCommand::Text {
+            position: Location::origin(),
+            text: String::from("top"),
+            style: style(1),
+        });
+        list.set_guides(vec![DrawCommand::Text {
+            position: Location::new(100.0, 100.0),
+            text: String::from("bottom"),
+            style: style(1),
+        }]);
+        assert_eq!(list.commands().len(), 1);
+        assert_eq!(list.prefix(0).guides().len(), 1);
+        match list.painted().next() {
+            Some(DrawCommand::Text { text, .. }) => assert_eq!(text, "bottom"),
+            _ => panic!("expected text"),
+        }
+        // Guides don't count towards the size of the drawing.
+        assert_eq!(
+            list.bounding_box(),
+            Some((Location::origin(), Location::origin()))
+        );
+    }
+
+    #[test]
+    fn pushed_commands_are_transformed() {
+        let mut list = DisplayList::new();
+        let _ = list.set_scale(2.0);
+        list.set_origin(Location::new(1.0, 1.0));
+        list.push(DrawCommand::Text {
+            position: Location::new(3.0, 4.0),
+            text: String::from("hi"),
+            style: style(1),
+        });
+        match &list.commands()[0] {
+            DrawCommand::Text { position, .. } => assert_eq!(*position, Location::new(7.0, 9.0)),
+            _ => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn poses_are_on_the_canvas() {
+        let mut list = DisplayList::new();
+        let _ = list.set_scale(2.0);
+        let _ = list.set_rotation(90.0);
+        let pose = |x: f64, visible: bool| Pose {
+            position: Location::new(x, 0.0),
+            heading: 0.0,
+            colour: COLORS[1],
+            visible,
+        };
+        list.set_pose(0, pose(1.0, true));
+        list.set_pose(1, pose(2.0, false));
+        list.push(DrawCommand::Text {
+            position: Location::origin(),
+            text: String::from("hi"),
+            style: style(1),
+        });
+        list.set_pose(0, pose(3.0, true));
+        assert_eq!(list.poses(0).len(), 2);
+        let (turtle, first) = list.poses(0)[0];
+        assert_eq!(turtle, 0);
+        assert!((first.position.y() + 2.0).abs() < 1e-9);
+        assert_eq!(first.heading, 90.0);
+        assert_eq!(list.pose_after(1).map(|(turtle, _)| turtle), Some(0));
+        // Only the turtle that is showing is painted, over everything else.
+        list.show_turtles();
+        assert_eq!(list.sprites().len(), 1);
+        assert!(matches!(
+            list.painted().last(),
+            Some(DrawCommand::Fill { .. })
+        ));
+        assert!(list.prefix(1).sprites().is_empty());
+    }
+
+    #[test]
+    fn set_background_colour() {
+        let mut list = DisplayList::new();
+        assert!(list.set_background_colour(4f32).is_ok());
+        assert_eq!(list.background(), COLORS[4]);
+    }
+
+    #[test]
+    fn set_background_out_of_palette() {
+        let mut list = DisplayList::new();
+        assert!(list.set_background_colour(16f32).is_err());
+        assert!(list.set_background_colour(1.5).is_err());
+        assert!(list.set_background_colour(-1f32).is_err());
+        assert_eq!(list.background(), COLORS[0]);
+    }
+
+    #[test]
+    fn transform_stack() {
+        let mut list = DisplayList::new();
+        list.push_transform();
+        let _ = list.set_rotation(-90.0);
+        assert_eq!(list.transform().rotation, 270.0);
+        assert!(list.pop_transform().is_ok());
+        assert!(list.transform().is_identity());
+        assert!(list.pop_transform().is_err());
+    }
+
+    #[test]
+    fn bad_scale() {
+        let mut list = DisplayList::new();
+        assert!(list.set_scale(0.0).is_err());
+        assert!(list.set_scale(f64::NAN).is_err());
+        assert!(list.set_rotation(f64::INFINITY).is_err());
+    }
+}
@@ -2,6 +2,7 @@ struct ErrorReporter {
     errors: Vec<Error>,
 }
 
+#[derive(Debug)]
 pub struct Error {
     message: String,
     location: (u32, u32),
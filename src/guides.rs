@@ -0,0 +1,108 @@
+use crate::canvas::Viewport;
+use crate::display::{DrawCommand, Ink, Style};
+use crate::location::Location;
+use unsvg::COLORS;
+
+/// The closest grid lines can be, one pixel apart at the default scale.
+pub const MIN_SPACING: f64 = 1f64;
+
+/// Lines to draw under a drawing to show where things are: a grid every `spacing` units
+/// and the x and y axes, both lined up with the turtle's origin and covering the image.
+pub fn guides(viewport: &Viewport, spacing: Option<f64>, axes: bool) -> Vec<DrawCommand> {
+    let (min, max) = viewport.area();
+    let line = |start: Location, end: Location, width: f64| DrawCommand::Line {
+        start,
+        end,
+        style: Style {
+            colour: COLORS[15],
+            width,
+            ink: Ink::Paint,
+            dash: None,
+        },
+    };
+
+    let mut commands = Vec::new();
+    if let Some(spacing) = spacing.map(|spacing| spacing.max(MIN_SPACING)) {
+        let mut x = (min.x() / spacing).ceil() * spacing;
+        while x <= max.x() {
+            commands.push(line(
+                Location::new(x, min.y()),
+                Location::new(x, max.y()),
+                0.5,
+            ));
+            x += spacing;
+        }
+        let mut y = (min.y() / spacing).ceil() * spacing;
+        while y <= max.y() {
+            commands.push(line(
+                Location::new(min.x(), y),
+                Location::new(max.x(), y),
+                0.5,
+            ));
+            y += spacing;
+        }
+    }
+    if axes {
+        commands.push(line(
+            Location::new(min.x(), 0f64),
+            Location::new(max.x(), 0f64),
+            2f64,
+        ));
+        commands.push(line(
+            Location::new(0f64, min.y()),
+            Location::new(0f64, max.y()),
+            2f64,
+        ));
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Canvas;
+    use crate::display::fixtures::style;
+    use crate::display::DisplayList;
+
+    fn viewport() -> Viewport {
+        Canvas::new(100, 60).viewport(&DisplayList::new())
+    }
+
+    #[test]
+    fn nothing_asked_for() {
+        assert!(guides(&viewport(), None, false).is_empty());
+    }
+
+    #[test]
+    fn grid_lines_through_origin() {
+        let commands = guides(&viewport(), Some(20.0), false);
+        // x at -40, -20, 0, 20, 40 and y at -20, 0, 20.
+        assert_eq!(commands.len(), 8);
+        assert!(commands.iter().any(|command| matches!(
+            command,
+            DrawCommand::Line { start, .. } if *start == Location::new(0.0, -30.0)
+        )));
+    }
+
+    #[test]
+    fn axes_cross_at_origin() {
+        let commands = guides(&viewport(), None, true);
+        assert_eq!(
+            commands[0],
+            DrawCommand::Line {
+                start: Location::new(-50.0, 0.0),
+                end: Location::new(50.0, 0.0),
+                style: Style {
+                    width: 2.0,
+                    ..style(15)
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn spacing_has_a_floor() {
+        // 1 unit apart at the least: x at -50..=50 and y at -30..=30.
+        assert_eq!(guides(&viewport(), Some(0.0001), false).len(), 101 + 61);
+    }
+}
@@ -0,0 +1,575 @@
+use crate::display::DisplayList;
+use crate::error::Error;
+use crate::location::Location;
+use crate::lsystem::LSystem;
+use crate::parser::{parse, Expression, Procedure, Statement, StatementKind};
+use crate::render::number;
+use crate::scanner::Scanner;
+use crate::token::TokenType;
+use crate::turtle::Boundary;
+use crate::turtles::Turtles;
+use std::collections::HashMap;
+use std::fmt;
+
+/// How deep blocks and procedure calls can nest before the program is stopped, so runaway
+/// recursion is an error rather than a stack overflow.
+const MAX_DEPTH: usize = 500;
+
+/// What an expression works out to. Numbers, TRUE and FALSE are usually written as words,
+/// so words are read as them wherever they are needed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Word(String),
+    Number(f64),
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn boolean(value: bool) -> Value {
+        Value::Word(String::from(if value { "TRUE" } else { "FALSE" }))
+    }
+
+    fn number(&self) -> Result<f64, String> {
+        let number = match self {
+            Value::Number(number) => Some(*number),
+            Value::Word(word) => word.parse::<f64>().ok(),
+            Value::List(_) => None,
+        };
+        number
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| format!("{self} is not a number"))
+    }
+
+    fn truth(&self) -> Result<bool, String> {
+        match self {
+            Value::Word(word) if word.eq_ignore_ascii_case("TRUE") => Ok(true),
+            Value::Word(word) if word.eq_ignore_ascii_case("FALSE") => Ok(false),
+            _ => Err(format!("{self} is not TRUE or FALSE")),
+        }
+    }
+
+    /// A point written as a list of two numbers, like `[3 4]`.
+    fn point(&self) -> Result<Location, String> {
+        match self {
+            Value::List(items) if items.len() == 2 => {
+                Ok(Location::new(items[0].number()?, items[1].number()?))
+            }
+            _ => Err(format!("{self} is not a list of two numbers")),
+        }
+    }
+
+    fn name(&self) -> Result<&str, String> {
+        match self {
+            Value::Word(word) if !word.is_empty() => Ok(word),
+            _ => Err(format!("{self} can't be used as a name")),
+        }
+    }
+
+    /// Whether two values are the same, as numbers if they both are.
+    fn equals(&self, other: &Value) -> bool {
+        match (self.number(), other.number()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => self.to_string() == other.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Word(word) => write!(f, "{word}"),
+            Value::Number(value) => write!(f, "{}", number(*value)),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(" "))
+            }
+        }
+    }
+}
+
+/// Runs a logo program with `turtles`, drawing into `drawing`. Errors say which line they
+/// happened on.
+pub fn run(source: &str, turtles: &mut Turtles, drawing: &mut DisplayList) -> Result<(), String> {
+    let mut scanner = Scanner::new(String::from(source));
+    scanner.scan().map_err(located)?;
+    let program = parse(scanner.tokens()).map_err(located)?;
+    let mut interpreter = Interpreter {
+        turtles,
+        drawing,
+        procedures: &program.procedures,
+        variables: HashMap::new(),
+        locals: Vec::new(),
+        depth: 0,
+    };
+    interpreter.block(&program.statements)
+}
+
+fn located(error: Error) -> String {
+    format!(
+        "Line {}, column {}: {}",
+        error.get_line(),
+        error.get_column(),
+        error.get_message()
+    )
+}
+
+struct Interpreter<'a> {
+    turtles: &'a mut Turtles,
+    drawing: &'a mut DisplayList,
+    procedures: &'a HashMap<String, Procedure>,
+    variables: HashMap<String, Value>,
+    /// The inputs of each procedure being run, innermost last.
+    locals: Vec<HashMap<String, Value>>,
+    /// How many blocks are being run, counting procedure bodies.
+    depth: usize,
+}
+
+impl Interpreter<'_> {
+    fn block(&mut self, statements: &[Statement]) -> Result<(), String> {
+        let Some(first) = statements.first() else {
+            return Ok(());
+        };
+        if self.depth >= MAX_DEPTH {
+            let line = first.line;
+            return Err(format!(
+                "Line {line}: Blocks and procedures are nested more than {MAX_DEPTH} deep"
+            ));
+        }
+        self.depth += 1;
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.statement(statement));
+        self.depth -= 1;
+        result
+    }
+
+    /// Runs a statement. Errors in blocks already carry their own line.
+    fn statement(&mut self, statement: &Statement) -> Result<(), String> {
+        let at_line = |message: String| format!("Line {}: {message}", statement.line);
+        self.turtles.current_mut().line = Some(statement.line);
+        match &statement.kind {
+            StatementKind::Command(token_type, inputs) => {
+                let inputs = self.evaluate_all(inputs).map_err(at_line)?;
+                self.command(*token_type, &inputs).map_err(at_line)
+            }
+            StatementKind::If(condition, body) => {
+                if self.condition(condition).map_err(at_line)? {
+                    self.block(body)?;
+                }
+                Ok(())
+            }
+            StatementKind::While(condition, body) => {
+                while self.condition(condition).map_err(at_line)? {
+                    self.block(body)?;
+                }
+                Ok(())
+            }
+            StatementKind::Ask(name, body) => {
+                let name = self.evaluate(name).map_err(at_line)?.to_string();
+                let previous = String::from(self.turtles.current_name());
+                self.turtles.tell(&name).map_err(at_line)?;
+                let result = self.block(body);
+                self.turtles.tell(&previous).map_err(at_line)?;
+                result
+            }
+            StatementKind::Call(name, inputs) => {
+                let inputs = self.evaluate_all(inputs).map_err(at_line)?;
+                let procedure = &self.procedures[name];
+                let locals = procedure.inputs.iter().cloned().zip(inputs).collect();
+                self.locals.push(locals);
+                let result = self.block(&procedure.body);
+                self.locals.pop();
+                result
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &Expression) -> Result<bool, String> {
+        self.evaluate(condition)?.truth()
+    }
+
+    fn command(&mut self, token_type: TokenType, inputs: &[Value]) -> Result<(), String> {
+        match token_type {
+            TokenType::Make => {
+                self.assign(inputs[0].name()?, inputs[1].clone());
+                return Ok(());
+            }
+            TokenType::AddAssign => {
+                let name = inputs[0].name()?;
+                let sum = self.lookup(name)?.number()? + inputs[1].number()?;
+                self.assign(name, Value::Number(sum));
+                return Ok(());
+            }
+            TokenType::NewTurtle => return self.turtles.new_turtle(inputs[0].name()?),
+            TokenType::Tell => return self.turtles.tell(inputs[0].name()?),
+            _ => {}
+        }
+
+        let drawing = &mut *self.drawing;
+        let turtle = self.turtles.current_mut();
+        match token_type {
+            TokenType::PenUp => turtle.pen_up(),
+            TokenType::PenDown => turtle.pen_down(),
+            TokenType::PenErase => turtle.pen_erase(),
+            TokenType::PenReverse => turtle.pen_reverse(),
+            TokenType::Forward => turtle.move_turtle(inputs[0].number()?, drawing)?,
+            TokenType::Back => turtle.move_turtle(-inputs[0].number()?, drawing)?,
+            TokenType::Left => turtle.move_sideways(-inputs[0].number()?, drawing)?,
+            TokenType::Right => turtle.move_sideways(inputs[0].number()?, drawing)?,
+            TokenType::SetPenColour => turtle.set_pen_colour(inputs[0].number()? as f32)?,
+            TokenType::Turn => turtle.turn(inputs[0].number()?)?,
+            TokenType::SetHeading => turtle.set_heading(inputs[0].number()?)?,
+            TokenType::SetX => turtle.set_x(inputs[0].number()?, drawing)?,
+            TokenType::SetY => turtle.set_y(inputs[0].number()?, drawing)?,
+            TokenType::Wrap => turtle.set_boundary(Boundary::Wrap),
+            TokenType::Fence => turtle.set_boundary(Boundary::Fence),
+            TokenType::Window => turtle.set_boundary(Boundary::Window),
+            TokenType::SetBackground => drawing.set_background_colour(inputs[0].number()? as f32)?,
+            TokenType::LSystem => {
+                let path = inputs[0].to_string();
+                let source = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Can't read the L-system {path}: {e}"))?;
+                let system: LSystem = source.parse()?;
+                turtle.run_lsystem(&system.expand()?, system.step, system.angle, drawing)?
+            }
+            TokenType::SetScale => drawing.set_scale(inputs[0].number()?)?,
+            TokenType::SetOrigin => drawing.set_origin(inputs[0].point()?),
+            TokenType::SetRotation => drawing.set_rotation(inputs[0].number()?)?,
+            TokenType::PushTransform => drawing.push_transform(),
+            TokenType::PopTransform => drawing.pop_transform()?,
+            TokenType::Arc => turtle.arc(inputs[0].number()?, inputs[1].number()?, drawing),
+            TokenType::Label => turtle.label(inputs[0].to_string(), drawing),
+            TokenType::BeginFill => turtle.begin_fill(),
+            TokenType::EndFill => turtle.end_fill(drawing)?,
+            TokenType::SetPenSize => turtle.set_pen_size(inputs[0].number()?)?,
+            TokenType::SetPenStyle => {
+                // A custom pattern comes as a list of lengths, like [6 2 1 2].
+                let style = match &inputs[0] {
+                    Value::List(items) => {
+                        let lengths: Vec<String> =
+                            items.iter().map(|item| item.to_string()).collect();
+                        lengths.join(" ")
+                    }
+                    other => other.to_string(),
+                };
+                turtle.set_pen_style(&style)?
+            }
+            TokenType::ShowTurtle => turtle.show_turtle(drawing),
+            TokenType::HideTurtle => turtle.hide_turtle(drawing),
+            TokenType::PushState => turtle.push_state(),
+            TokenType::PopState => turtle.pop_state()?,
+            TokenType::SetPos => {
+                let target = inputs[0].point()?;
+                turtle.set_pos(target.x(), target.y(), drawing)?
+            }
+            TokenType::Home => turtle.home(drawing)?,
+            other => unreachable!("{} is not a command", other.to_string()),
+        }
+        Ok(())
+    }
+
+    fn evaluate_all(&self, expressions: &[Expression]) -> Result<Vec<Value>, String> {
+        expressions
+            .iter()
+            .map(|expression| self.evaluate(expression))
+            .collect()
+    }
+
+    fn evaluate(&self, expression: &Expression) -> Result<Value, String> {
+        match expression {
+            Expression::Word(word) | Expression::Number(word) => Ok(Value::Word(word.clone())),
+            Expression::Variable(name) => self.lookup(name).cloned(),
+            Expression::List(items) => Ok(Value::List(self.evaluate_all(items)?)),
+            Expression::Apply(token_type, inputs) => {
+                let inputs = self.evaluate_all(inputs)?;
+                self.report(*token_type, &inputs)
+            }
+        }
+    }
+
+    /// Works out an operator or query.
+    fn report(&self, token_type: TokenType, inputs: &[Value]) -> Result<Value, String> {
+        let turtle = self.turtles.current();
+        let value = match token_type {
+            TokenType::XCor => Value::Number(turtle.position.x()),
+            TokenType::YCor => Value::Number(turtle.position.y()),
+            TokenType::Heading => Value::Number(turtle.heading()),
+            TokenType::Colour => Value::Number(turtle.colour as f64),
+            TokenType::Pos => Value::List(turtle.pos().into_iter().map(Value::Number).collect()),
+            TokenType::Towards => Value::Number(turtle.towards(&inputs[0].point()?)),
+            TokenType::Distance => Value::Number(turtle.distance(&inputs[0].point()?)),
+            TokenType::PenMode => Value::Word(String::from(turtle.pen_mode())),
+            TokenType::Plus => Value::Number(inputs[0].number()? + inputs[1].number()?),
+            TokenType::Minus => Value::Number(inputs[0].number()? - inputs[1].number()?),
+            TokenType::Star => Value::Number(inputs[0].number()? * inputs[1].number()?),
+            TokenType::Slash => {
+                let divisor = inputs[1].number()?;
+                if divisor == 0f64 {
+                    return Err(String::from("Can't divide by zero"));
+                }
+                Value::Number(inputs[0].number()? / divisor)
+            }
+            TokenType::Eq => Value::boolean(inputs[0].equals(&inputs[1])),
+            TokenType::Ne => Value::boolean(!inputs[0].equals(&inputs[1])),
+            TokenType::Gt => Value::boolean(inputs[0].number()? > inputs[1].number()?),
+            TokenType::Lt => Value::boolean(inputs[0].number()? < inputs[1].number()?),
+            TokenType::And => Value::boolean(inputs[0].truth()? && inputs[1].truth()?),
+            TokenType::Or => Value::boolean(inputs[0].truth()? || inputs[1].truth()?),
+            other => unreachable!("{} doesn't report a value", other.to_string()),
+        };
+        Ok(value)
+    }
+
+    /// A procedure input if the innermost procedure has one called `name`, or else a
+    /// variable.
+    fn lookup(&self, name: &str) -> Result<&Value, String> {
+        self.locals
+            .last()
+            .and_then(|locals| locals.get(name))
+            .or_else(|| self.variables.get(name))
+            .ok_or_else(|| format!("There is no variable called {name}"))
+    }
+
+    fn assign(&mut self, name: &str, value: Value) {
+        match self.locals.last_mut() {
+            Some(locals) if locals.contains_key(name) => {
+                locals.insert(String::from(name), value);
+            }
+            _ => {
+                self.variables.insert(String::from(name), value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{DrawCommand, Ink};
+    use crate::turtle::Turtle;
+    use unsvg::COLORS;
+
+    fn run_program(source: &str) -> Result<(Turtles, DisplayList), String> {
+        let mut turtles = Turtles::new(Turtle::new());
+        let mut drawing = DisplayList::new();
+        run(source, &mut turtles, &mut drawing)?;
+        Ok((turtles, drawing))
+    }
+
+    fn position(turtles: &Turtles) -> (f64, f64) {
+        let position = &turtles.current().position;
+        (position.x(), position.y())
+    }
+
+    #[test]
+    fn moves_and_turns() {
+        let (turtles, drawing) =
+            run_program("PENDOWN\nFORWARD \"10\nTURN \"90\nFORWARD \"5\nBACK \"2").unwrap();
+        assert_eq!(position(&turtles), (3f64, 10f64));
+        assert_eq!(drawing.commands().len(), 3);
+    }
+
+    #[test]
+    fn left_and_right_move_sideways() {
+        let (turtles, _) = run_program("LEFT \"90\nFORWARD \"50\nRIGHT \"45").unwrap();
+        assert_eq!(position(&turtles), (-45f64, 50f64));
+        assert_eq!(turtles.current().heading(), 0f64);
+    }
+
+    #[test]
+    fn variables_and_queries() {
+        let source = "SETX \"10\nSETY \"20\nMAKE \"X XCOR\nMAKE \"Y YCOR\nADDASSIGN \"X :Y\nSETPENCOLOR \"3\nSETX + :X COLOR";
+        let (turtles, _) = run_program(source).unwrap();
+        assert_eq!(position(&turtles), (33f64, 20f64));
+    }
+
+    #[test]
+    fn if_and_while() {
+        let source = "MAKE \"n \"0\nWHILE LT :n \"4 [\n  IF NE :n \"2 [ FORWARD \"1 ]\n  ADDASSIGN \"n \"1\n]";
+        let (turtles, _) = run_program(source).unwrap();
+        assert_eq!(position(&turtles), (0f64, 3f64));
+    }
+
+    #[test]
+    fn arithmetic_and_logic() {
+        // (3 < 3 + 1) && (9 > 8) || (8 / 2 < 8 / 3)
+        let source = "MAKE \"x OR AND LT \"3 + \"3 \"1 GT \"9 \"8 LT / \"8 \"2 / \"8 \"3\nIF :x [ FORWARD - \"5.0 \"3.5 ]";
+        let (turtles, _) = run_program(source).unwrap();
+        assert_eq!(position(&turtles), (0f64, 1.5));
+        assert!(run_program("FORWARD / \"1 \"0").is_err());
+        assert!(run_program("IF \"1 [ PENDOWN ]").is_err());
+    }
+
+    #[test]
+    fn procedures_have_their_own_inputs() {
+        let source = "TO Side \"length\n  FORWARD :length\n  TURN \"90\nEND\nMAKE \"length \"1\nSide \"5\nSide :length";
+        let (turtles, _) = run_program(source).unwrap();
+        assert_eq!(position(&turtles), (1f64, 5f64));
+    }
+
+    #[test]
+    fn runaway_recursion_stops() {
+        let error = run_program("TO Again\n  Again\nEND\nAgain").err().unwrap();
+        assert!(error.contains("deep"));
+        let error =
+            run_program("TO Again\n  IF EQ \"1 \"1 [ WHILE EQ \"1 \"1 [ Again ] ]\nEND\nAgain")
+                .err()
+                .unwrap();
+        assert!(error.contains("deep"));
+    }
+
+    #[test]
+    fn errors_say_where() {
+        assert_eq!(
+            run_program("PENDOWN\nBACK \"hello!").err(),
+            Some(String::from("Line 2: hello! is not a number"))
+        );
+        assert_eq!(
+            run_program("WHILE \"TRUE [\n  FORWARD :missing\n]").err(),
+            Some(String::from("Line 2: There is no variable called missing"))
+        );
+        assert_eq!(
+            run_program("PENDOWN \"1").err(),
+            Some(String::from(
+                "Line 1, column 9: Expected a command but found \"1"
+            ))
+        );
+        assert!(run_program("ADDASSIGN \"missing \"1").is_err());
+    }
+
+    #[test]
+    fn set_background() {
+        let (_, drawing) = run_program("SETBACKGROUND \"4").unwrap();
+        assert_eq!(drawing.background(), COLORS[4]);
+        assert!(run_program("SETBACKGROUND \"16").is_err());
+    }
+
+    #[test]
+    fn shapes_and_text() {
+        let source = "PENDOWN\nSETPENSIZE \"3\nBEGINFILL\nFORWARD \"10\nTURN \"90\nFORWARD \"10\nENDFILL\nARC \"90 \"5\nLABEL \"hi";
+        let (_, drawing) = run_program(source).unwrap();
+        let kinds: Vec<&str> = drawing
+            .commands()
+            .iter()
+            .map(|command| match command {
+                DrawCommand::Line { style, .. } => {
+                    assert_eq!(style.width, 3f64);
+                    "line"
+                }
+                DrawCommand::Arc { .. } => "arc",
+                DrawCommand::Fill { .. } => "fill",
+                DrawCommand::Text { .. } => "text",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["line", "line", "fill", "arc", "text"]);
+    }
+
+    #[test]
+    fn show_and_hide_the_turtle() {
+        let (turtles, drawing) =
+            run_program("PENDOWN\nSHOWTURTLE\nFORWARD \"5\nHIDETURTLE").unwrap();
+        assert!(!turtles.current().visible);
+        assert!(drawing.poses(0)[0].1.visible);
+        assert!(!drawing.poses(1)[0].1.visible);
+    }
+
+    #[test]
+    fn push_and_pop_state() {
+        let source = "PUSHSTATE\nSETPENCOLOR \"2\nTURN \"90\nFORWARD \"5\nPOPSTATE\nFORWARD \"1";
+        let (turtles, _) = run_program(source).unwrap();
+        assert_eq!(position(&turtles), (0f64, 1f64));
+        assert_eq!(turtles.current().colour, 7f32);
+        assert!(run_program("PUSHSTATE\nPOPSTATE\nPOPSTATE").is_err());
+    }
+
+    #[test]
+    fn positions_and_points() {
+        let source = "SETPOS [3 4]\nMAKE \"far DISTANCE [0 0]\nSETHEADING TOWARDS [3 10]\nFORWARD :far\nMAKE \"here POS\nHOME\nSETPOS :here";
+        let (turtles, _) = run_program(source).unwrap();
+        assert_eq!(position(&turtles), (3f64, 9f64));
+        assert_eq!(turtles.current().heading(), 0f64);
+        assert!(run_program("SETPOS \"3").is_err());
+        assert!(run_program("SETPOS [3 4 5]").is_err());
+    }
+
+    #[test]
+    fn pen_modes() {
+        let source = "PENERASE\nIF EQ PENMODE \"ERASE [ FORWARD \"1 ]\nPENREVERSE\nIF EQ PENMODE \"REVERSE [ FORWARD \"1 ]\nPENDOWN\nIF EQ PENMODE \"PAINT [ FORWARD \"1 ]";
+        let (turtles, drawing) = run_program(source).unwrap();
+        assert_eq!(position(&turtles), (0f64, 3f64));
+        let inks: Vec<Ink> = drawing
+            .commands()
+            .iter()
+            .map(|command| match command {
+                DrawCommand::Line { style, .. } => style.ink,
+                _ => panic!("expected a line"),
+            })
+            .collect();
+        assert_eq!(inks, vec![Ink::Erase, Ink::Reverse, Ink::Paint]);
+    }
+
+    #[test]
+    fn pen_styles() {
+        let source = "PENDOWN\nSETPENSTYLE \"dash\nFORWARD \"1\nSETPENSTYLE [6 2 1 2]\nFORWARD \"1\nSETPENSTYLE \"solid\nFORWARD \"1";
+        let (_, drawing) = run_program(source).unwrap();
+        let dashes: Vec<Option<Vec<f64>>> = drawing
+            .commands()
+            .iter()
+            .map(|command| match command {
+                DrawCommand::Line { style, .. } => {
+                    style.dash.as_ref().map(|dash| dash.pattern.clone())
+                }
+                _ => panic!("expected a line"),
+            })
+            .collect();
+        assert_eq!(
+            dashes,
+            vec![
+                Some(vec![8f64, 4f64]),
+                Some(vec![6f64, 2f64, 1f64, 2f64]),
+                None
+            ]
+        );
+        assert!(run_program("SETPENSTYLE \"wavy").is_err());
+        assert!(run_program("SETPENSTYLE [1 \"x]").is_err());
+    }
+
+    #[test]
+    fn edges_and_transforms() {
+        let mut turtles = Turtles::new(Turtle::new());
+        turtles.current_mut().set_bounds(10f64, 10f64);
+        let mut drawing = DisplayList::new();
+        let source =
+            "FENCE\nPUSHTRANSFORM\nSETSCALE \"2\nSETORIGIN [1 \"2]\nPOPTRANSFORM\nFORWARD \"20";
+        assert!(run(source, &mut turtles, &mut drawing).is_err());
+        assert!(drawing.transform().is_identity());
+        let source = "WRAP\nFORWARD \"15";
+        assert!(run(source, &mut turtles, &mut drawing).is_ok());
+        assert_eq!(position(&turtles), (0f64, -5f64));
+    }
+
+    #[test]
+    fn tell_and_ask() {
+        let source =
+            "NEWTURTLE \"bob\nASK \"bob [ FORWARD \"5 ]\nFORWARD \"1\nTELL \"bob\nFORWARD \"1";
+        let (mut turtles, _) = run_program(source).unwrap();
+        assert_eq!(position(&turtles), (0f64, 6f64));
+        let _ = turtles.tell("turtle");
+        assert_eq!(position(&turtles), (0f64, 1f64));
+        assert!(run_program("TELL \"nobody").is_err());
+    }
+
+    #[test]
+    fn lsystem_from_a_file() {
+        let path = std::env::temp_dir().join("rslogo_interpreter_test.lsys");
+        std::fs::write(
+            &path,
+            "axiom F\nangle 90\nstep 10\nrule F = F+F\niterations 1\n",
+        )
+        .unwrap();
+        let source = format!("PENDOWN\nLSYSTEM \"{}", path.display());
+        let (_, drawing) = run_program(&source).unwrap();
+        assert_eq!(drawing.commands().len(), 2);
+        let _ = std::fs::remove_file(path);
+    }
+}
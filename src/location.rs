@@ -1,7 +1,7 @@
 #[derive(Debug, Clone)]
 pub struct Location {
-    x: f32,
-    y: f32,
+    x: f64,
+    y: f64,
 }
 
 impl Location {
@@ -9,7 +9,7 @@ impl Location {
         Location { x: 0.0, y: 0.0 }
     }
 
-    pub fn new(x: f32, y: f32) -> Location {
+    pub fn new(x: f64, y: f64) -> Location {
         Location { x, y }
     }
 }
@@ -21,17 +21,37 @@ impl PartialEq for Location {
 }
 
 impl Location {
-    pub fn x(&self) -> f32 {
+    pub fn x(&self) -> f64 {
         self.x
     }
 
-    pub fn y(&self) -> f32 {
+    pub fn y(&self) -> f64 {
         self.y
     }
 
     pub fn translate(&self, other: &Self) -> Self {
         Location::new(self.x + other.x, self.y + other.y)
     }
+
+    /// How far this is from the origin, treating it as a vector.
+    pub fn length(&self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    /// The direction of this vector in degrees anticlockwise from the x axis, in
+    /// (-180, 180].
+    pub fn angle(&self) -> f64 {
+        self.y.atan2(self.x).to_degrees()
+    }
+}
+
+/// The vector from `other` to `self`.
+impl std::ops::Sub for &Location {
+    type Output = Location;
+
+    fn sub(self, other: &Location) -> Location {
+        Location::new(self.x - other.x, self.y - other.y)
+    }
 }
 
 #[cfg(test)]
@@ -76,4 +96,23 @@ mod tests {
         let l3 = l1.translate(&l2);
         assert_eq!(l3, l1);
     }
+
+    #[test]
+    fn testing_subtraction() {
+        let l1 = Location::new(2.0, 3.0);
+        let l2 = Location::new(-4.0, 5.0);
+        assert_eq!(&l1 - &l2, Location::new(6.0, -2.0));
+    }
+
+    #[test]
+    fn testing_length() {
+        assert_eq!(Location::new(-3.0, 4.0).length(), 5.0);
+    }
+
+    #[test]
+    fn testing_angle() {
+        assert_eq!(Location::new(1.0, 0.0).angle(), 0.0);
+        assert_eq!(Location::new(0.0, 2.0).angle(), 90.0);
+        assert_eq!(Location::new(-1.0, 0.0).angle(), 180.0);
+    }
 }
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+/// The most symbols an expansion may grow to before it is treated as a mistake.
+const MAX_SYMBOLS: usize = 5_000_000;
+
+/// An L-system: a string of symbols rewritten by its rules, then drawn by a turtle.
+///
+/// Read from a `.lsys` file with one setting per line and `//` comments:
+///
+/// ```text
+/// axiom F
+/// rule F = F+F-F-F+F
+/// iterations 3
+/// step 10
+/// angle 90
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LSystem {
+    pub axiom: String,
+    pub rules: HashMap<char, String>,
+    pub iterations: u32,
+    /// How far F and f move.
+    pub step: f64,
+    /// How far + and - turn, in degrees.
+    pub angle: f64,
+}
+
+impl std::str::FromStr for LSystem {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut axiom = None;
+        let mut rules = HashMap::new();
+        let mut iterations = None;
+        let mut step = 10f64;
+        let mut angle = 90f64;
+        for (index, line) in source.lines().enumerate() {
+            let line = line.split("//").next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| format!("Line {}: {message}", index + 1);
+            let (setting, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let number = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|number| number.is_finite())
+                    .ok_or_else(|| error(&format!("'{value}' isn't a number")))
+            };
+            match setting.to_ascii_lowercase().as_str() {
+                "axiom" => axiom = Some(value.replace(char::is_whitespace, "")),
+                "rule" => {
+                    let (symbol, replacement) = value
+                        .split_once('=')
+                        .ok_or_else(|| error("a rule looks like F = F+F"))?;
+                    let mut symbol = symbol.trim().chars();
+                    let (Some(symbol), None) = (symbol.next(), symbol.next()) else {
+                        return Err(error("a rule replaces a single symbol"));
+                    };
+                    rules.insert(symbol, replacement.replace(char::is_whitespace, ""));
+                }
+                "iterations" => {
+                    let count = value
+                        .parse()
+                        .map_err(|_| error(&format!("'{value}' isn't a whole number")))?;
+                    iterations = Some(count);
+                }
+                "step" => step = number()?,
+                "angle" => angle = number()?,
+                _ => return Err(error(&format!("unknown setting '{setting}'"))),
+            }
+        }
+        Ok(LSystem {
+            axiom: axiom.ok_or("An L-system needs an axiom")?,
+            rules,
+            iterations: iterations.unwrap_or(1),
+            step,
+            angle,
+        })
+    }
+}
+
+impl LSystem {
+    /// Applies the rules to the axiom `iterations` times. Symbols without a rule stay as
+    /// they are.
+    pub fn expand(&self) -> Result<String, String> {
+        let mut symbols = self.axiom.clone();
+        for _ in 0..self.iterations {
+            let mut next = String::with_capacity(symbols.len());
+            for symbol in symbols.chars() {
+                match self.rules.get(&symbol) {
+                    Some(replacement) => next.push_str(replacement),
+                    None => next.push(symbol),
+                }
+                if next.len() > MAX_SYMBOLS {
+                    return Err(format!(
+                        "The L-system grows past {MAX_SYMBOLS} symbols, try fewer iterations"
+                    ));
+                }
+            }
+            symbols = next;
+        }
+        Ok(symbols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KOCH: &str = "// Koch curve
+axiom F
+rule F = F+F-F-F+F
+iterations 2
+step 5
+angle 90
+";
+
+    #[test]
+    fn parses_settings() {
+        let system: LSystem = KOCH.parse().unwrap();
+        assert_eq!(system.axiom, "F");
+        assert_eq!(system.rules.get(&'F'), Some(&String::from("F+F-F-F+F")));
+        assert_eq!(
+            (system.iterations, system.step, system.angle),
+            (2, 5.0, 90.0)
+        );
+    }
+
+    #[test]
+    fn expands_rules() {
+        let mut system: LSystem = KOCH.parse().unwrap();
+        system.iterations = 1;
+        assert_eq!(system.expand(), Ok(String::from("F+F-F-F+F")));
+        system.iterations = 2;
+        assert_eq!(system.expand().unwrap().matches('F').count(), 25);
+    }
+
+    #[test]
+    fn symbols_without_rules_stay() {
+        let system: LSystem = "axiom X[F]\nrule X = FX".parse().unwrap();
+        assert_eq!(system.expand(), Ok(String::from("FX[F]")));
+    }
+
+    #[test]
+    fn bad_files() {
+        assert!("rule F = FF".parse::<LSystem>().is_err());
+        assert_eq!(
+            "axiom F\nrule FF = F".parse::<LSystem>(),
+            Err(String::from("Line 2: a rule replaces a single symbol"))
+        );
+        assert!("axiom F\nangle ninety".parse::<LSystem>().is_err());
+        assert!("axiom F\ncolour 3".parse::<LSystem>().is_err());
+    }
+
+    #[test]
+    fn runaway_growth() {
+        let system: LSystem = "axiom F\nrule F = FFFFFFFFFF\niterations 9"
+            .parse()
+            .unwrap();
+        assert!(system.expand().is_err());
+    }
+}
@@ -1,58 +1,449 @@
-use clap::Parser;
-use unsvg::Image;
+use canvas::Canvas;
+use clap::{CommandFactory, Parser};
+use display::DisplayList;
+use lsystem::LSystem;
+use render::animation::{FrameOptions, FrameStep};
+use render::gcode::{GcodeOptions, Origin, PenLift};
+use render::page::PageSize;
+use render::png::{Background, PngOptions, BASE_DPI};
+use render::svg::Pace;
+use render::term::{Glyphs, TermOptions};
+use turtle::{AngleConvention, Mode, Turtle};
+use turtles::Turtles;
+pub mod canvas;
+pub mod display;
 pub mod error;
+pub mod guides;
+pub mod interpreter;
 pub mod location;
+pub mod lsystem;
+pub mod output;
+pub mod parser;
+pub mod render;
 pub mod scanner;
 pub mod token;
+pub mod trace;
+pub mod transform;
 pub mod turtle;
+pub mod turtles;
+
+/// The ways a g-code plotter can lift its pen.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum PenLiftKind {
+    Z,
+    Servo,
+}
 
 /// A simple program to parse four arguments using clap.
 #[derive(Parser)]
 struct Args {
-    /// Path to a file
+    /// Path to a logo program, or an L-system in a .lsys file
     file_path: std::path::PathBuf,
 
-    /// Path to an svg or png image
-    image_path: std::path::PathBuf,
+    /// Path to an svg, png, gif, pdf, eps, gcode, hpgl or plt file, or a json or csv trace
+    #[arg(required_unless_present_any = ["term", "output"])]
+    image_path: Option<std::path::PathBuf>,
+
+    /// Another file to write, as many times as needed. Every output comes from the same run
+    #[arg(short, long, value_name = "PATH")]
+    output: Vec<std::path::PathBuf>,
+
+    /// Height, needed unless --auto-size is given
+    height: Option<u32>,
+
+    /// Width, needed unless --auto-size is given
+    width: Option<u32>,
+
+    /// Size the image to fit the drawing, with a margin in pixels around it
+    #[arg(long, value_name = "MARGIN", num_args = 0..=1, default_missing_value = "10")]
+    auto_size: Option<u32>,
+
+    /// Animate the drawing being traced out in order: an svg traces itself and a png
+    /// becomes an animated png. Gifs are always animated
+    #[arg(long)]
+    animate: bool,
+
+    /// Merge lines in an svg into polylines and drop repeated ones to shrink the file
+    #[arg(long, conflicts_with = "animate")]
+    optimise_svg: bool,
+
+    /// How many seconds the animation takes
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        requires = "animate",
+        conflicts_with = "speed"
+    )]
+    duration: Option<f64>,
+
+    /// How many units a second the animated turtle moves
+    #[arg(long, value_name = "UNITS", requires = "animate")]
+    speed: Option<f64>,
+
+    /// Take a gif or animated png frame after this many drawing commands
+    #[arg(
+        long,
+        value_name = "COMMANDS",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with = "frame_distance"
+    )]
+    frame_every: u32,
+
+    /// Take a gif or animated png frame each time the pen travels this far
+    #[arg(long, value_name = "UNITS", value_parser = positive)]
+    frame_distance: Option<f64>,
+
+    /// How long each gif or animated png frame is shown, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 100)]
+    frame_delay: u16,
+
+    /// How many times a gif or animated png plays, 0 for forever
+    #[arg(long, default_value_t = 0)]
+    loops: u16,
+
+    /// Draw the turtle where it finished, and as it goes in animations, unless the program
+    /// hides it
+    #[arg(long)]
+    show_turtle: bool,
+
+    /// Draw a grid under the drawing with lines this many units apart
+    #[arg(long, value_name = "UNITS", value_parser = grid_spacing)]
+    grid: Option<f64>,
+
+    /// Draw the x and y axes under the drawing
+    #[arg(long)]
+    axes: bool,
+
+    /// Png pixels per canvas unit, for high resolution exports
+    #[arg(
+        long,
+        value_name = "FACTOR",
+        default_value_t = 1f64,
+        conflicts_with = "dpi"
+    )]
+    scale: f64,
+
+    /// Png resolution in dots per inch, where 96 is one pixel per canvas unit
+    #[arg(long)]
+    dpi: Option<f64>,
+
+    /// Render a png this many times larger and average it down for smoother edges
+    #[arg(long, value_name = "FACTOR", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=16))]
+    supersample: u32,
+
+    /// What is behind the drawing in a png
+    #[arg(long, value_enum, default_value_t = Background::Solid)]
+    background: Background,
+
+    /// Page size for pdf and eps output, e.g. A4, Letter, 210x297mm or 8.5x11in. The
+    /// drawing is scaled to fit. Without it each pixel is a point.
+    #[arg(long)]
+    page: Option<PageSize>,
+
+    /// How a g-code plotter lifts its pen
+    #[arg(long, value_enum, default_value_t = PenLiftKind::Z)]
+    pen_lift: PenLiftKind,
+
+    /// Pen up Z height in millimetres or servo angle for g-code [default: 5 or 50]
+    #[arg(long, value_name = "VALUE", allow_negative_numbers = true)]
+    pen_up: Option<f64>,
+
+    /// Pen down Z height in millimetres or servo angle for g-code [default: 0 or 30]
+    #[arg(long, value_name = "VALUE", allow_negative_numbers = true)]
+    pen_down: Option<f64>,
+
+    /// G-code drawing speed in millimetres a minute
+    #[arg(long, value_name = "MM_PER_MIN", default_value_t = 1000f64, value_parser = positive)]
+    feed_rate: f64,
+
+    /// G-code pen up travel speed in millimetres a minute
+    #[arg(long, value_name = "MM_PER_MIN", default_value_t = 3000f64, value_parser = positive)]
+    travel_rate: f64,
+
+    /// Millimetres per turtle unit in g-code and hpgl
+    #[arg(long, value_name = "MM", default_value_t = 1f64, value_parser = positive)]
+    mm_per_unit: f64,
+
+    /// Where the plotter's 0, 0 is in g-code
+    #[arg(long, value_enum, default_value_t = Origin::Turtle)]
+    origin: Origin,
+
+    /// Draw g-code and hpgl strokes in program order instead of reordering them to cut down on
+    /// pen up travel
+    #[arg(long)]
+    no_optimise: bool,
+
+    /// Preview the drawing in the terminal, sized to its width
+    #[arg(long, value_enum, value_name = "GLYPHS", num_args = 0..=1, default_missing_value = "braille")]
+    term: Option<Glyphs>,
+
+    /// Colour the terminal preview with the pen colours
+    #[arg(long, requires = "term")]
+    term_colour: bool,
+
+    /// Make SETX, SETY and SETPOS jump without drawing even with the pen down, as older
+    /// versions did
+    #[arg(long)]
+    teleport: bool,
+
+    /// How headings are measured
+    #[arg(long, value_enum, default_value_t = AngleConvention::Compass)]
+    angle_convention: AngleConvention,
+}
+
+impl Args {
+    /// Parses the command line, exiting with a usage message if it doesn't make sense.
+    fn parse_sized() -> Self {
+        Args::parse().sized().unwrap_or_else(|e| e.exit())
+    }
+
+    /// Checks there is a size unless the canvas is auto sized. Positionals are filled in
+    /// order, so when every output is given with `-o` the height lands in `image_path` and
+    /// the width in `height`; those are moved back to where they belong first.
+    fn sized(mut self) -> Result<Self, clap::Error> {
+        if self.width.is_none() && !self.output.is_empty() {
+            let height = self
+                .image_path
+                .as_ref()
+                .and_then(|path| path.to_str())
+                .and_then(|path| path.parse::<u32>().ok());
+            if let Some(height) = height {
+                self.width = self.height;
+                self.height = Some(height);
+                self.image_path = None;
+            }
+        }
+        if self.auto_size.is_none() && (self.height.is_none() || self.width.is_none()) {
+            return Err(Args::command().error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "A height and width are needed unless --auto-size is given",
+            ));
+        }
+        Ok(self)
+    }
+}
+
+/// Parses a number that has to be above zero.
+fn positive(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() && number > 0f64 => Ok(number),
+        _ => Err(format!("'{value}' isn't a positive number")),
+    }
+}
 
-    /// Height
-    height: u32,
+/// Parses a grid spacing, which can't be so small the grid has more lines than pixels.
+fn grid_spacing(value: &str) -> Result<f64, String> {
+    match positive(value)? {
+        spacing if spacing >= guides::MIN_SPACING => Ok(spacing),
+        _ => Err(format!(
+            "The grid spacing must be at least {}",
+            guides::MIN_SPACING
+        )),
+    }
+}
 
-    /// Width
-    width: u32,
+/// Reads an L-system from a `.lsys` file and draws it with the pen down.
+fn run_lsystem(
+    path: &std::path::Path,
+    turtle: &mut Turtle,
+    drawing: &mut DisplayList,
+) -> Result<(), String> {
+    let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let system: LSystem = source.parse()?;
+    turtle.mode = Mode::PenDown;
+    turtle.run_lsystem(&system.expand()?, system.step, system.angle, drawing)
 }
 
 fn main() -> Result<(), ()> {
-    let args: Args = Args::parse();
+    let args = Args::parse_sized();
 
     // Access the parsed arguments
     let file_path = args.file_path;
     let image_path = args.image_path;
-    let height = args.height;
-    let width = args.width;
 
-    let image = Image::new(width, height);
-
-    match image_path.extension().map(|s| s.to_str()).flatten() {
-        Some("svg") => {
-            let res = image.save_svg(&image_path);
-            if let Err(e) = res {
-                eprintln!("Error saving svg: {e}");
-                return Err(());
-            }
+    let canvas = match (args.auto_size, args.height, args.width) {
+        (Some(margin), _, _) => Canvas::auto_sized(margin),
+        (None, Some(height), Some(width)) => Canvas::new(width, height),
+        _ => unreachable!("Args::sized requires a height and width without --auto-size"),
+    };
+    let mut turtle = Turtle::with_convention(args.angle_convention);
+    if let Some((half_width, half_height)) = canvas.half_extents() {
+        turtle.set_bounds(half_width, half_height);
+    }
+    turtle.visible = args.show_turtle;
+    turtle.teleport = args.teleport;
+    let mut turtles = Turtles::new(turtle);
+    let mut drawing = DisplayList::new();
+    // So an animation starts with the turtle where it begins.
+    turtles.current().record_pose(&mut drawing);
+    if file_path.extension().and_then(|s| s.to_str()) == Some("lsys") {
+        if let Err(e) = run_lsystem(&file_path, turtles.current_mut(), &mut drawing) {
+            eprintln!("Error drawing L-system: {e}");
+            return Err(());
         }
-        Some("png") => {
-            let res = image.save_png(&image_path);
-            if let Err(e) = res {
-                eprintln!("Error saving png: {e}");
+    } else {
+        let source = match std::fs::read_to_string(&file_path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Error reading program: {e}");
                 return Err(());
             }
-        }
-        _ => {
-            eprintln!("File extension not supported");
+        };
+        if let Err(e) = interpreter::run(&source, &mut turtles, &mut drawing) {
+            eprintln!("Error running program: {e}");
             return Err(());
         }
     }
+    turtles.show(&mut drawing);
+    // Guides go in once the drawing is finished so they cover an auto sized canvas, and
+    // they stay inside it so they don't make it grow.
+    if args.grid.is_some() || args.axes {
+        let viewport = canvas.viewport(&drawing);
+        drawing.set_guides(guides::guides(&viewport, args.grid, args.axes));
+    }
+
+    let pace = match (args.duration, args.speed) {
+        (_, Some(speed)) => Pace::Speed(speed),
+        (Some(seconds), None) => Pace::Duration(seconds),
+        (None, None) => Pace::Duration(5f64),
+    };
+    let (Pace::Speed(value) | Pace::Duration(value)) = pace;
+    if !(value.is_finite() && value > 0f64) {
+        eprintln!("The animation duration and speed must be positive");
+        return Err(());
+    }
+
+    let scale = args.dpi.map_or(args.scale, |dpi| dpi / BASE_DPI);
+    if !(scale.is_finite() && scale > 0f64) {
+        eprintln!("The scale and dpi must be positive");
+        return Err(());
+    }
+    let png_options = PngOptions {
+        scale,
+        supersample: args.supersample,
+        background: args.background,
+    };
+
+    let frame_options = FrameOptions {
+        step: match args.frame_distance {
+            Some(distance) => FrameStep::Distance(distance),
+            None => FrameStep::Commands(args.frame_every as usize),
+        },
+        delay: args.frame_delay,
+        loops: args.loops,
+    };
+
+    let gcode_options = GcodeOptions {
+        pen: match args.pen_lift {
+            PenLiftKind::Z => PenLift::Z {
+                up: args.pen_up.unwrap_or(5f64),
+                down: args.pen_down.unwrap_or(0f64),
+            },
+            PenLiftKind::Servo => PenLift::Servo {
+                up: args.pen_up.unwrap_or(50f64),
+                down: args.pen_down.unwrap_or(30f64),
+            },
+        },
+        draw_feed: args.feed_rate,
+        travel_feed: args.travel_rate,
+        scale: args.mm_per_unit,
+        origin: args.origin,
+        optimise: !args.no_optimise,
+    };
+
+    if let Some(glyphs) = args.term {
+        let options = TermOptions {
+            glyphs,
+            colour: args.term_colour,
+            columns: render::term::terminal_columns(),
+        };
+        print!(
+            "{}",
+            render::term::to_term(&drawing, &canvas.viewport(&drawing), &options)
+        );
+    }
+
+    let mut outputs: Vec<&std::path::Path> = image_path.iter().map(|path| path.as_path()).collect();
+    outputs.extend(args.output.iter().map(|path| path.as_path()));
+    let run = output::Run {
+        drawing: &drawing,
+        trace: &turtles.current().trace,
+        canvas: &canvas,
+        animate: args.animate,
+        optimise_svg: args.optimise_svg,
+        pace,
+        frames: frame_options,
+        page: args.page,
+        png: png_options,
+        gcode: gcode_options,
+        optimise: !args.no_optimise,
+        mm_per_unit: args.mm_per_unit,
+    };
+    if let Err(e) = output::save_all(&run, &outputs) {
+        eprintln!("{e}");
+        return Err(());
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::iter::once("rslogo").chain(args.iter().copied()))?.sized()
+    }
+
+    #[test]
+    fn image_path_and_size() {
+        let args = parse(&["prog.lg", "out.svg", "200", "100"]).unwrap();
+        assert_eq!(args.image_path, Some(PathBuf::from("out.svg")));
+        assert_eq!((args.height, args.width), (Some(200), Some(100)));
+    }
+
+    #[test]
+    fn outputs_with_size() {
+        let args = parse(&["prog.lg", "-o", "a.svg", "-o", "b.png", "200", "100"]).unwrap();
+        assert_eq!(args.image_path, None);
+        assert_eq!(args.output.len(), 2);
+        assert_eq!((args.height, args.width), (Some(200), Some(100)));
+    }
+
+    #[test]
+    fn outputs_alongside_image_path() {
+        let args = parse(&["prog.lg", "out.svg", "-o", "b.png", "200", "100"]).unwrap();
+        assert_eq!(args.image_path, Some(PathBuf::from("out.svg")));
+        assert_eq!((args.height, args.width), (Some(200), Some(100)));
+    }
+
+    #[test]
+    fn plotter_settings_must_be_positive() {
+        for setting in ["--feed-rate", "--travel-rate", "--mm-per-unit"] {
+            for value in ["0", "-5", "NaN"] {
+                assert!(parse(&["prog.lg", "out.gcode", "200", "100", setting, value]).is_err());
+            }
+            assert!(parse(&["prog.lg", "out.gcode", "200", "100", setting, "2.5"]).is_ok());
+        }
+    }
+
+    #[test]
+    fn frame_steps_must_be_positive() {
+        let gif = |flag: &str, value: &str| parse(&["prog.lg", "out.gif", "9", "9", flag, value]);
+        assert!(gif("--frame-every", "0").is_err());
+        assert!(gif("--frame-every", "3").is_ok());
+        for value in ["0", "-1", "NaN", "inf"] {
+            assert!(gif("--frame-distance", value).is_err());
+        }
+        assert!(gif("--frame-distance", "0.5").is_ok());
+    }
+
+    #[test]
+    fn size_needed_without_auto_size() {
+        assert!(parse(&["prog.lg", "out.svg", "200"]).is_err());
+        assert!(parse(&["prog.lg", "-o", "a.svg", "200"]).is_err());
+        assert!(parse(&["prog.lg", "-o", "a.svg", "--auto-size"]).is_ok());
+    }
+}
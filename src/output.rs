@@ -0,0 +1,197 @@
+use crate::canvas::Canvas;
+use crate::display::DisplayList;
+use crate::render;
+use crate::render::animation::FrameOptions;
+use crate::render::gcode::GcodeOptions;
+use crate::render::page::PageSize;
+use crate::render::png::PngOptions;
+use crate::render::svg::Pace;
+use crate::trace::{self, Trace};
+use std::path::Path;
+
+/// Everything a finished run produced, and how the user asked for it to be written.
+pub struct Run<'a> {
+    pub drawing: &'a DisplayList,
+    pub trace: &'a Trace,
+    pub canvas: &'a Canvas,
+    pub animate: bool,
+    pub optimise_svg: bool,
+    pub pace: Pace,
+    pub frames: FrameOptions,
+    pub page: Option<PageSize>,
+    pub png: PngOptions,
+    pub gcode: GcodeOptions,
+    pub optimise: bool,
+    pub mm_per_unit: f64,
+}
+
+/// A way of writing a run to a file.
+pub struct Backend {
+    /// What errors call the output.
+    pub name: &'static str,
+    /// File extensions that pick this backend.
+    pub extensions: &'static [&'static str],
+    pub save: fn(&Run, &Path) -> Result<(), String>,
+}
+
+/// Every output format, looked up by file extension.
+pub const BACKENDS: &[Backend] = &[
+    Backend {
+        name: "svg",
+        extensions: &["svg"],
+        save: |run, path| {
+            if run.animate {
+                render::svg::save_animated_svg(run.drawing, run.canvas, run.pace, path)
+            } else if run.optimise_svg {
+                render::svg::save_optimised_svg(run.drawing, run.canvas, path)
+            } else {
+                render::svg::save_svg(run.drawing, run.canvas, path)
+            }
+        },
+    },
+    Backend {
+        name: "png",
+        extensions: &["png"],
+        save: |run, path| {
+            if run.animate {
+                render::animation::save_apng(run.drawing, run.canvas, &run.frames, &run.png, path)
+            } else {
+                render::png::save_png(run.drawing, run.canvas, &run.png, path)
+            }
+        },
+    },
+    Backend {
+        name: "gif",
+        extensions: &["gif"],
+        save: |run, path| {
+            render::animation::save_gif(run.drawing, run.canvas, &run.frames, &run.png, path)
+        },
+    },
+    Backend {
+        name: "pdf",
+        extensions: &["pdf"],
+        save: |run, path| render::pdf::save_pdf(run.drawing, run.canvas, run.page, path),
+    },
+    Backend {
+        name: "eps",
+        extensions: &["eps"],
+        save: |run, path| render::eps::save_eps(run.drawing, run.canvas, run.page, path),
+    },
+    Backend {
+        name: "gcode",
+        extensions: &["gcode"],
+        save: |run, path| render::gcode::save_gcode(run.drawing, run.canvas, &run.gcode, path),
+    },
+    Backend {
+        name: "hpgl",
+        extensions: &["hpgl", "plt"],
+        save: |run, path| {
+            render::hpgl::save_hpgl(run.drawing, run.canvas, run.mm_per_unit, run.optimise, path)
+        },
+    },
+    Backend {
+        name: "json",
+        extensions: &["json"],
+        save: |run, path| trace::save_json(run.trace, path),
+    },
+    Backend {
+        name: "csv",
+        extensions: &["csv"],
+        save: |run, path| trace::save_csv(run.trace, path),
+    },
+];
+
+/// The backend for `path`'s extension.
+pub fn backend_for(path: &Path) -> Option<&'static Backend> {
+    let extension = path.extension()?.to_str()?;
+    BACKENDS
+        .iter()
+        .find(|backend| backend.extensions.contains(&extension))
+}
+
+/// Writes the run to every path, checking they all have a known extension before writing
+/// any of them.
+pub fn save_all(run: &Run, paths: &[&Path]) -> Result<(), String> {
+    let backends = paths
+        .iter()
+        .map(|path| {
+            backend_for(path)
+                .ok_or_else(|| format!("File extension not supported: {}", path.display()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (backend, path) in backends.iter().zip(paths) {
+        (backend.save)(run, path).map_err(|e| format!("Error saving {}: {e}", backend.name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::animation::FrameStep;
+    use crate::render::gcode::{Origin, PenLift};
+
+    fn run<'a>(drawing: &'a DisplayList, trace: &'a Trace, canvas: &'a Canvas) -> Run<'a> {
+        Run {
+            drawing,
+            trace,
+            canvas,
+            animate: false,
+            optimise_svg: false,
+            pace: Pace::Duration(5f64),
+            frames: FrameOptions {
+                step: FrameStep::Commands(1),
+                delay: 100,
+                loops: 0,
+            },
+            page: None,
+            png: PngOptions::default(),
+            gcode: GcodeOptions {
+                pen: PenLift::Z { up: 5.0, down: 0.0 },
+                draw_feed: 1000.0,
+                travel_feed: 3000.0,
+                scale: 1.0,
+                origin: Origin::Turtle,
+                optimise: true,
+            },
+            optimise: true,
+            mm_per_unit: 1.0,
+        }
+    }
+
+    #[test]
+    fn backends_by_extension() {
+        assert_eq!(backend_for(Path::new("a.plt")).unwrap().name, "hpgl");
+        assert_eq!(backend_for(Path::new("dir/a.svg")).unwrap().name, "svg");
+        assert!(backend_for(Path::new("a.bmp")).is_none());
+        assert!(backend_for(Path::new("svg")).is_none());
+    }
+
+    #[test]
+    fn writes_every_output() {
+        let (drawing, trace, canvas) = (DisplayList::new(), Trace::new(), Canvas::new(10, 10));
+        let dir = std::env::temp_dir();
+        let svg = dir.join("rslogo_output_test.svg");
+        let csv = dir.join("rslogo_output_test.csv");
+        let res = save_all(&run(&drawing, &trace, &canvas), &[&svg, &csv]);
+        assert!(res.is_ok());
+        assert!(std::fs::read_to_string(&svg).unwrap().starts_with("<svg"));
+        assert!(std::fs::read_to_string(&csv).unwrap().starts_with("kind,"));
+        let _ = std::fs::remove_file(svg);
+        let _ = std::fs::remove_file(csv);
+    }
+
+    #[test]
+    fn unknown_extension_writes_nothing() {
+        let (drawing, trace, canvas) = (DisplayList::new(), Trace::new(), Canvas::new(10, 10));
+        let dir = std::env::temp_dir();
+        let svg = dir.join("rslogo_output_unwritten.svg");
+        let _ = std::fs::remove_file(&svg);
+        let res = save_all(
+            &run(&drawing, &trace, &canvas),
+            &[&svg, Path::new("out.bmp")],
+        );
+        assert!(res.is_err());
+        assert!(!svg.exists());
+    }
+}
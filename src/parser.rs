@@ -0,0 +1,412 @@
+use crate::error::Error;
+use crate::token::{Token, TokenType};
+use std::collections::HashMap;
+
+/// Something that works out a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// A quoted word like `"50` or `"TRUE`, without its quote.
+    Word(String),
+    /// A bare number, as written inside lists like `[3 4]`.
+    Number(String),
+    /// A variable like `:size`, without its colon.
+    Variable(String),
+    List(Vec<Expression>),
+    /// An operator or query and its inputs, like `+ "1 :x` or `XCOR`.
+    Apply(TokenType, Vec<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    /// A built in command and its inputs.
+    Command(TokenType, Vec<Expression>),
+    If(Expression, Vec<Statement>),
+    While(Expression, Vec<Statement>),
+    /// Runs the block with the turtle named by the expression.
+    Ask(Expression, Vec<Statement>),
+    /// Runs the procedure with this name.
+    Call(String, Vec<Expression>),
+}
+
+/// A statement and the source line it starts on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub line: usize,
+}
+
+/// A procedure made with `TO name "input ... END`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Procedure {
+    pub inputs: Vec<String>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+    pub procedures: HashMap<String, Procedure>,
+}
+
+/// How many inputs a built in command takes, or `None` if the token isn't one.
+fn command_inputs(token_type: TokenType) -> Option<usize> {
+    match token_type {
+        TokenType::PenUp
+        | TokenType::PenDown
+        | TokenType::Wrap
+        | TokenType::Fence
+        | TokenType::Window
+        | TokenType::PushTransform
+        | TokenType::PopTransform
+        | TokenType::BeginFill
+        | TokenType::EndFill
+        | TokenType::ShowTurtle
+        | TokenType::HideTurtle
+        | TokenType::PushState
+        | TokenType::PopState
+        | TokenType::Home
+        | TokenType::PenErase
+        | TokenType::PenReverse => Some(0),
+        TokenType::Forward
+        | TokenType::Back
+        | TokenType::Left
+        | TokenType::Right
+        | TokenType::SetPenColour
+        | TokenType::Turn
+        | TokenType::SetHeading
+        | TokenType::SetX
+        | TokenType::SetY
+        | TokenType::SetBackground
+        | TokenType::NewTurtle
+        | TokenType::Tell
+        | TokenType::LSystem
+        | TokenType::SetScale
+        | TokenType::SetOrigin
+        | TokenType::SetRotation
+        | TokenType::Label
+        | TokenType::SetPenSize
+        | TokenType::SetPos
+        | TokenType::SetPenStyle => Some(1),
+        TokenType::Make | TokenType::AddAssign | TokenType::Arc => Some(2),
+        _ => None,
+    }
+}
+
+/// How many inputs an operator or query takes, or `None` if the token doesn't report a
+/// value.
+fn reporter_inputs(token_type: TokenType) -> Option<usize> {
+    match token_type {
+        TokenType::XCor
+        | TokenType::YCor
+        | TokenType::Heading
+        | TokenType::Colour
+        | TokenType::Pos
+        | TokenType::PenMode => Some(0),
+        TokenType::Towards | TokenType::Distance => Some(1),
+        TokenType::Plus
+        | TokenType::Minus
+        | TokenType::Star
+        | TokenType::Slash
+        | TokenType::Eq
+        | TokenType::Ne
+        | TokenType::Gt
+        | TokenType::Lt
+        | TokenType::And
+        | TokenType::Or => Some(2),
+        _ => None,
+    }
+}
+
+/// Turns tokens into a program. Every command and procedure takes a fixed number of inputs,
+/// so statements need no separators.
+pub fn parse(tokens: &[Token]) -> Result<Program, Error> {
+    let mut parser = Parser {
+        tokens,
+        current: 0,
+        arities: arities(tokens),
+    };
+    let mut program = Program::default();
+    while let Some(token) = parser.peek() {
+        if token.token_type() == TokenType::To {
+            let (name, procedure) = parser.procedure()?;
+            if program.procedures.insert(name.clone(), procedure).is_some() {
+                return Err(located(token, format!("{name} is defined twice")));
+            }
+        } else {
+            program.statements.push(parser.statement()?);
+        }
+    }
+    Ok(program)
+}
+
+/// How many inputs each procedure takes, found up front so procedures can be called before
+/// they are defined.
+fn arities(tokens: &[Token]) -> HashMap<String, usize> {
+    let mut arities = HashMap::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if token.token_type() != TokenType::To {
+            continue;
+        }
+        if let Some(name) = tokens.get(index + 1) {
+            let inputs = tokens[index + 2..]
+                .iter()
+                .take_while(|token| token.token_type() == TokenType::Value)
+                .count();
+            arities.insert(String::from(name.lexeme()), inputs);
+        }
+    }
+    arities
+}
+
+fn located(token: &Token, message: String) -> Error {
+    Error::new(message, (token.line(), token.column()))
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    current: usize,
+    arities: HashMap<String, usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.current)
+    }
+
+    fn next(&mut self, expected: &str) -> Result<&'a Token, Error> {
+        match self.tokens.get(self.current) {
+            Some(token) => {
+                self.current += 1;
+                Ok(token)
+            }
+            None => {
+                let end = self.tokens.last().map_or((1, 1), |token| {
+                    (token.line(), token.column() + token.lexeme().len() as u32)
+                });
+                Err(Error::new(
+                    format!("Expected {expected} but the program ended"),
+                    end,
+                ))
+            }
+        }
+    }
+
+    fn procedure(&mut self) -> Result<(String, Procedure), Error> {
+        self.next("TO")?;
+        let name = self.next("a procedure name")?;
+        if name.token_type() != TokenType::Word {
+            return Err(located(
+                name,
+                format!("{} can't be used as a procedure name", name.lexeme()),
+            ));
+        }
+        let mut inputs = Vec::new();
+        while let Some(token) = self.peek() {
+            if token.token_type() != TokenType::Value {
+                break;
+            }
+            inputs.push(String::from(&token.lexeme()[1..]));
+            self.current += 1;
+        }
+        let mut body = Vec::new();
+        loop {
+            let token = self.peek();
+            match token.map(|token| token.token_type()) {
+                Some(TokenType::End) => {
+                    self.current += 1;
+                    break;
+                }
+                Some(TokenType::To) => {
+                    return Err(located(
+                        token.unwrap(),
+                        String::from("Procedures can't be defined inside other procedures"),
+                    ))
+                }
+                Some(_) => body.push(self.statement()?),
+                None => {
+                    self.next(&format!("END for {}", name.lexeme()))?;
+                }
+            }
+        }
+        Ok((String::from(name.lexeme()), Procedure { inputs, body }))
+    }
+
+    fn statement(&mut self) -> Result<Statement, Error> {
+        let token = self.next("a command")?;
+        let line = token.line() as usize;
+        let kind = match token.token_type() {
+            TokenType::If => StatementKind::If(self.expression()?, self.block()?),
+            TokenType::While => StatementKind::While(self.expression()?, self.block()?),
+            TokenType::Ask => StatementKind::Ask(self.expression()?, self.block()?),
+            TokenType::Word => {
+                let inputs = match self.arities.get(token.lexeme()) {
+                    Some(inputs) => *inputs,
+                    None => {
+                        return Err(located(
+                            token,
+                            format!("There is no procedure called {}", token.lexeme()),
+                        ))
+                    }
+                };
+                StatementKind::Call(String::from(token.lexeme()), self.inputs(inputs)?)
+            }
+            TokenType::End => {
+                return Err(located(token, String::from("END without a matching TO")))
+            }
+            token_type => match command_inputs(token_type) {
+                Some(inputs) => StatementKind::Command(token_type, self.inputs(inputs)?),
+                None => {
+                    return Err(located(
+                        token,
+                        format!("Expected a command but found {}", token.lexeme()),
+                    ))
+                }
+            },
+        };
+        Ok(Statement { kind, line })
+    }
+
+    fn inputs(&mut self, count: usize) -> Result<Vec<Expression>, Error> {
+        (0..count).map(|_| self.expression()).collect()
+    }
+
+    /// Statements between `[` and `]`.
+    fn block(&mut self) -> Result<Vec<Statement>, Error> {
+        let open = self.next("[")?;
+        if open.token_type() != TokenType::LeftBracket {
+            return Err(located(
+                open,
+                format!("Expected [ but found {}", open.lexeme()),
+            ));
+        }
+        let mut statements = Vec::new();
+        loop {
+            match self.peek().map(|token| token.token_type()) {
+                Some(TokenType::RightBracket) => {
+                    self.current += 1;
+                    return Ok(statements);
+                }
+                Some(_) => statements.push(self.statement()?),
+                None => {
+                    self.next("]")?;
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression, Error> {
+        let token = self.next("a value")?;
+        match token.token_type() {
+            TokenType::Value => Ok(Expression::Word(String::from(&token.lexeme()[1..]))),
+            TokenType::Identifier => Ok(Expression::Variable(String::from(&token.lexeme()[1..]))),
+            TokenType::Number => Ok(Expression::Number(String::from(token.lexeme()))),
+            TokenType::LeftBracket => {
+                let mut items = Vec::new();
+                loop {
+                    match self.peek().map(|token| token.token_type()) {
+                        Some(TokenType::RightBracket) => {
+                            self.current += 1;
+                            return Ok(Expression::List(items));
+                        }
+                        Some(_) => items.push(self.expression()?),
+                        None => {
+                            self.next("]")?;
+                        }
+                    }
+                }
+            }
+            token_type => match reporter_inputs(token_type) {
+                Some(inputs) => Ok(Expression::Apply(token_type, self.inputs(inputs)?)),
+                None => Err(located(
+                    token,
+                    format!("Expected a value but found {}", token.lexeme()),
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn parse_source(source: &str) -> Result<Program, Error> {
+        let mut scanner = Scanner::new(String::from(source));
+        scanner.scan()?;
+        parse(scanner.tokens())
+    }
+
+    #[test]
+    fn commands_take_their_inputs() {
+        let program = parse_source("PENDOWN\nFORWARD + \"1 :x").unwrap();
+        assert_eq!(program.statements.len(), 2);
+        assert_eq!(program.statements[1].line, 2);
+        assert_eq!(
+            program.statements[1].kind,
+            StatementKind::Command(
+                TokenType::Forward,
+                vec![Expression::Apply(
+                    TokenType::Plus,
+                    vec![
+                        Expression::Word(String::from("1")),
+                        Expression::Variable(String::from("x"))
+                    ]
+                )]
+            )
+        );
+    }
+
+    #[test]
+    fn blocks_and_lists() {
+        let program = parse_source("IF EQ \"1 \"1 [ SETORIGIN [3 :y] ]").unwrap();
+        match &program.statements[0].kind {
+            StatementKind::If(_, body) => assert_eq!(
+                body[0].kind,
+                StatementKind::Command(
+                    TokenType::SetOrigin,
+                    vec![Expression::List(vec![
+                        Expression::Number(String::from("3")),
+                        Expression::Variable(String::from("y"))
+                    ])]
+                )
+            ),
+            _ => panic!("expected an if"),
+        }
+    }
+
+    #[test]
+    fn procedures_can_be_called_before_they_are_defined() {
+        let program = parse_source("Box \"5\nTO Box \"size\n FORWARD :size\nEND").unwrap();
+        assert_eq!(program.procedures["Box"].inputs, vec![String::from("size")]);
+        assert_eq!(
+            program.statements[0].kind,
+            StatementKind::Call(
+                String::from("Box"),
+                vec![Expression::Word(String::from("5"))]
+            )
+        );
+    }
+
+    #[test]
+    fn wrong_number_of_inputs() {
+        // One too many leaves a value where a command should be.
+        let error = parse_source("FORWARD \"10 \"20").err().unwrap();
+        assert_eq!(error.get_location(), (1, 13));
+        assert!(parse_source("SETPENCOLOR").is_err());
+    }
+
+    #[test]
+    fn unbalanced_blocks_and_procedures() {
+        assert!(parse_source("IF EQ \"TRUE \"TRUE\nEND").is_err());
+        assert!(parse_source("WHILE EQ \"TRUE \"TRUE [\n PENDOWN").is_err());
+        assert!(parse_source("TO Box \"Arg\n FORWARD :Arg").is_err());
+        assert!(parse_source("FORWARD \"1\nEND").is_err());
+    }
+
+    #[test]
+    fn unknown_procedure() {
+        let error = parse_source("LEFT \"1\nRIGHT GOODNIGHT").err().unwrap();
+        assert_eq!(error.get_location(), (2, 7));
+    }
+}
@@ -0,0 +1,369 @@
+use crate::canvas::{Canvas, Viewport};
+use crate::display::{DisplayList, DrawCommand, Pose};
+use crate::render::png::{image_size, rasterise_with, straight_rgba, PngOptions};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// When a new frame of an animation is taken.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameStep {
+    /// After every so many drawing commands.
+    Commands(usize),
+    /// After the turtle's pen has travelled so many units.
+    Distance(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameOptions {
+    pub step: FrameStep,
+    /// How long each frame is shown, in milliseconds.
+    pub delay: u16,
+    /// How many times the animation plays, 0 meaning forever.
+    pub loops: u16,
+}
+
+/// Writes `drawing` to an animated gif, with each frame rasterised as a png would be.
+pub fn save_gif<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    options: &FrameOptions,
+    png: &PngOptions,
+    path: P,
+) -> Result<(), String> {
+    let viewport = canvas.viewport(drawing);
+    let (width, height) = gif_dimensions(image_size(&viewport, png))?;
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder =
+        gif::Encoder::new(BufWriter::new(file), width, height, &[]).map_err(|e| e.to_string())?;
+    // Without a repeat extension a gif plays once.
+    let repeat = match options.loops {
+        0 => Some(gif::Repeat::Infinite),
+        1 => None,
+        loops => Some(gif::Repeat::Finite(loops - 1)),
+    };
+    if let Some(repeat) = repeat {
+        encoder.set_repeat(repeat).map_err(|e| e.to_string())?;
+    }
+
+    for frame in frames(drawing, options.step) {
+        let mut pixels = rgba(&frame, &viewport, png)?;
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+        frame.delay = options.delay / 10;
+        encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Writes `drawing` to an animated png, with each frame rasterised as a png would be.
+pub fn save_apng<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    options: &FrameOptions,
+    png: &PngOptions,
+    path: P,
+) -> Result<(), String> {
+    let viewport = canvas.viewport(drawing);
+    let frames = frames(drawing, options.step);
+    let (width, height) = image_size(&viewport, png);
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, options.loops as u32)
+        .map_err(|e| e.to_string())?;
+    encoder
+        .set_frame_delay(options.delay, 1000)
+        .map_err(|e| e.to_string())?;
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    for frame in &frames {
+        writer
+            .write_image_data(&rgba(frame, &viewport, png)?)
+            .map_err(|e| e.to_string())?;
+    }
+    writer.finish().map_err(|e| e.to_string())
+}
+
+fn gif_dimensions((width, height): (u32, u32)) -> Result<(u16, u16), String> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(String::from(
+            "Gifs can be at most 65535 pixels wide and high",
+        )),
+    }
+}
+
+/// Rasterises a frame to straight (not premultiplied) rgba bytes.
+fn rgba(frame: &DisplayList, viewport: &Viewport, png: &PngOptions) -> Result<Vec<u8>, String> {
+    Ok(straight_rgba(&rasterise_with(frame, viewport, png)?))
+}
+
+/// Splits `drawing` into the frames of an animation of it being drawn. Each frame holds
+/// everything drawn so far with the turtles that are showing on top, where they were then.
+pub fn frames(drawing: &DisplayList, step: FrameStep) -> Vec<DisplayList> {
+    let commands = drawing.commands();
+    let mut frames = vec![with_turtles(drawing, 0)];
+    match step {
+        FrameStep::Commands(every) => {
+            let mut len = 0;
+            while len < commands.len() {
+                len = (len + every.max(1)).min(commands.len());
+                frames.push(with_turtles(drawing, len));
+            }
+        }
+        FrameStep::Distance(every) => {
+            let mut travelled = 0f64;
+            let mut next_frame = every;
+            for (index, command) in commands.iter().enumerate() {
+                let length = command.length();
+                while every > 0f64 && travelled + length > next_frame {
+                    let partial = command.partial((next_frame - travelled) / length);
+                    let sprites = part_way(drawing, index, &partial)
+                        .iter()
+                        .filter_map(Pose::sprite)
+                        .collect();
+                    let mut frame = drawing.prefix(index);
+                    frame.push(partial);
+                    frame.set_sprites(sprites);
+                    frames.push(frame);
+                    next_frame += every;
+                }
+                travelled += length;
+            }
+            if !commands.is_empty() {
+                frames.push(with_turtles(drawing, commands.len()));
+            }
+        }
+    }
+    frames
+}
+
+/// The first `len` commands with the turtles that were showing by then on top.
+fn with_turtles(drawing: &DisplayList, len: usize) -> DisplayList {
+    let mut frame = drawing.prefix(len);
+    frame.set_sprites(
+        drawing
+            .poses(len)
+            .into_iter()
+            .filter_map(|(_, pose)| pose.sprite())
+            .collect(),
+    );
+    frame
+}
+
+/// The turtles' poses while the command at `index` has been drawn as far as `partial`.
+/// The turtle drawing it is at the end of the part drawn so far, and the others are where
+/// they were before it.
+fn part_way(drawing: &DisplayList, index: usize, partial: &DrawCommand) -> Vec<Pose> {
+    let mut poses: Vec<(usize, Pose)> = drawing
+        .poses(index)
+        .into_iter()
+        .map(|(turtle, pose)| (turtle, pose.clone()))
+        .collect();
+    if let Some((turtle, after)) = drawing.pose_after(index + 1) {
+        let mut pose = after.clone();
+        if let DrawCommand::Line { end, .. } = partial {
+            pose.position = end.clone();
+        }
+        match poses.iter_mut().find(|(other, _)| *other == turtle) {
+            Some(last) => last.1 = pose,
+            None => poses.push((turtle, pose)),
+        }
+    }
+    poses.into_iter().map(|(_, pose)| pose).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::fixtures::line;
+    use crate::location::Location;
+    use crate::render::png::Background;
+    use unsvg::COLORS;
+
+    fn pose(x: f64, y: f64, heading: f64) -> Pose {
+        Pose {
+            position: Location::new(x, y),
+            heading,
+            colour: COLORS[2],
+            visible: true,
+        }
+    }
+
+    /// Three sides of a square, drawn by a turtle that is showing.
+    fn square() -> DisplayList {
+        let mut drawing = DisplayList::new();
+        drawing.set_pose(0, pose(0.0, 0.0, 0.0));
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 2));
+        drawing.set_pose(0, pose(0.0, 10.0, 0.0));
+        drawing.push(line((0.0, 10.0), (10.0, 10.0), 2));
+        drawing.set_pose(0, pose(10.0, 10.0, 90.0));
+        drawing.push(line((10.0, 10.0), (10.0, 0.0), 2));
+        drawing.set_pose(0, pose(10.0, 0.0, 180.0));
+        drawing
+    }
+
+    #[test]
+    fn frame_every_command() {
+        let frames = frames(&square(), FrameStep::Commands(1));
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].commands().len(), 0);
+        assert_eq!(frames[3].commands().len(), 3);
+        // Each frame has the turtle drawn on top.
+        assert!(frames.iter().all(|frame| frame.sprites().len() == 1));
+    }
+
+    #[test]
+    fn frame_every_two_commands() {
+        let frames = frames(&square(), FrameStep::Commands(2));
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].commands().len(), 3);
+        assert_eq!(frames[2].sprites().len(), 1);
+    }
+
+    #[test]
+    fn frame_by_distance_splits_lines() {
+        let frames = frames(&square(), FrameStep::Distance(4.0));
+        // Frames at 4, 8, ..., 28 and the finished drawing.
+        assert_eq!(frames.len(), 9);
+        assert_eq!(frames[1].commands()[0], line((0.0, 0.0), (0.0, 4.0), 2));
+    }
+
+    #[test]
+    fn first_frame_keeps_background() {
+        let mut drawing = square();
+        drawing.set_background(COLORS[3]);
+        let frames = frames(&drawing, FrameStep::Commands(1));
+        assert!(frames.iter().all(|frame| frame.background() == COLORS[3]));
+    }
+
+    #[test]
+    fn frames_are_not_transformed_twice() {
+        let mut drawing = DisplayList::new();
+        let _ = drawing.set_scale(2.0);
+        let _ = drawing.set_rotation(90.0);
+        drawing.set_origin(Location::new(5.0, 5.0));
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 2));
+        drawing.set_pose(0, pose(0.0, 10.0, 0.0));
+        let frames = frames(&drawing, FrameStep::Distance(10.0));
+        // The line runs from (5, 5) to (25, 5) on the canvas, so halfway is (15, 5).
+        assert_eq!(frames[1].commands()[0], line((5.0, 5.0), (15.0, 5.0), 2));
+        for frame in &frames[1..] {
+            match frame.sprites().last() {
+                Some(DrawCommand::Fill { points, .. }) => {
+                    assert!((points[0].y() - 5.0).abs() < 1e-9);
+                    assert!(points[0].x() > 14.0);
+                }
+                _ => panic!("expected the turtle"),
+            }
+        }
+    }
+
+    #[test]
+    fn turtle_is_where_it_was() {
+        let frames = frames(&square(), FrameStep::Commands(1));
+        assert_eq!(
+            frames[2].sprites(),
+            &[DrawCommand::turtle_sprite(
+                &Location::new(10.0, 10.0),
+                90.0,
+                COLORS[2]
+            )]
+        );
+    }
+
+    #[test]
+    fn turtle_follows_line_part_way() {
+        let frames = frames(&square(), FrameStep::Distance(4.0));
+        assert_eq!(
+            frames[1].sprites(),
+            &[DrawCommand::turtle_sprite(
+                &Location::new(0.0, 4.0),
+                0.0,
+                COLORS[2]
+            )]
+        );
+    }
+
+    #[test]
+    fn hidden_turtle_is_not_drawn() {
+        let mut drawing = square();
+        drawing.set_pose(
+            0,
+            Pose {
+                visible: false,
+                ..pose(10.0, 0.0, 180.0)
+            },
+        );
+        let frames = frames(&drawing, FrameStep::Commands(1));
+        assert_eq!(frames[2].sprites().len(), 1);
+        assert!(frames[3].sprites().is_empty());
+    }
+
+    #[test]
+    fn turtles_are_drawn_once() {
+        let mut drawing = square();
+        drawing.show_turtles();
+        assert_eq!(drawing.sprites().len(), 1);
+        for frame in frames(&drawing, FrameStep::Commands(1)) {
+            assert_eq!(frame.sprites().len(), 1);
+            assert!(frame
+                .commands()
+                .iter()
+                .all(|command| matches!(command, DrawCommand::Line { .. })));
+        }
+    }
+
+    #[test]
+    fn gif_too_big() {
+        let viewport = Canvas::new(70000, 10).viewport(&DisplayList::new());
+        assert!(gif_dimensions(image_size(&viewport, &PngOptions::default())).is_err());
+    }
+
+    #[test]
+    fn writes_gif_and_apng() {
+        let directory = std::env::temp_dir();
+        let gif = directory.join("rslogo_animation_test.gif");
+        let apng = directory.join("rslogo_animation_test.png");
+        let options = FrameOptions {
+            step: FrameStep::Commands(1),
+            delay: 100,
+            loops: 0,
+        };
+        let canvas = Canvas::new(32, 32);
+        let png = PngOptions::default();
+        assert!(save_gif(&square(), &canvas, &options, &png, &gif).is_ok());
+        assert!(save_apng(&square(), &canvas, &options, &png, &apng).is_ok());
+        let gif_bytes = std::fs::read(&gif).unwrap();
+        let apng_bytes = std::fs::read(&apng).unwrap();
+        assert!(gif_bytes.starts_with(b"GIF89a"));
+        assert!(apng_bytes.windows(4).any(|chunk| chunk == b"acTL"));
+        let _ = std::fs::remove_file(gif);
+        let _ = std::fs::remove_file(apng);
+    }
+
+    #[test]
+    fn frames_use_png_options() {
+        let apng = std::env::temp_dir().join("rslogo_animation_scaled_test.png");
+        let options = FrameOptions {
+            step: FrameStep::Commands(1),
+            delay: 100,
+            loops: 0,
+        };
+        let png = PngOptions {
+            scale: 2f64,
+            supersample: 2,
+            background: Background::Transparent,
+        };
+        assert!(save_apng(&square(), &Canvas::new(32, 16), &options, &png, &apng).is_ok());
+        let decoder = png::Decoder::new(File::open(&apng).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (64, 32));
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        // Nothing is drawn in the corner of the first frame, so it is see-through.
+        assert_eq!(pixels[3], 0);
+        let _ = std::fs::remove_file(apng);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::canvas::Canvas;
+use crate::display::DisplayList;
+use crate::render::number;
+use crate::render::page::{layout, Page, PageSize, Segment, Shape};
+use crate::render::pdf::{escape, rgb};
+
+/// Writes `drawing` to an encapsulated postscript file.
+pub fn save_eps<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    page: Option<PageSize>,
+    path: P,
+) -> Result<(), String> {
+    let eps = to_eps(&layout(drawing, canvas, page));
+    std::fs::write(path, eps).map_err(|e| e.to_string())
+}
+
+/// Builds an eps document holding `page`.
+pub fn to_eps(page: &Page) -> String {
+    let mut eps = format!(
+        "%!PS-Adobe-3.0 EPSF-3.0\n%%BoundingBox: 0 0 {} {}\n%%HiResBoundingBox: 0 0 {} {}\n%%EndComments\n",
+        page.size.width.ceil(),
+        page.size.height.ceil(),
+        number(page.size.width),
+        number(page.size.height)
+    );
+    let canvas = &page.canvas;
+    eps.push_str(&format!(
+        "{} {} {} {} rectclip\n",
+        number(canvas.x),
+        number(canvas.y),
+        number(canvas.width),
+        number(canvas.height)
+    ));
+    for shape in &page.shapes {
+        match shape {
+            Shape::Stroke {
+                path,
+                colour,
+                width,
+            } => {
+                eps.push_str(&format!(
+                    "{} setrgbcolor {} setlinewidth newpath ",
+                    rgb(colour),
+                    number(*width)
+                ));
+                eps.push_str(&path_operators(path));
+                eps.push_str("stroke\n");
+            }
+            Shape::Fill { path, colour } => {
+                eps.push_str(&format!("{} setrgbcolor newpath ", rgb(colour)));
+                eps.push_str(&path_operators(path));
+                eps.push_str("closepath fill\n");
+            }
+            Shape::Text {
+                at,
+                size,
+                text,
+                colour,
+            } => eps.push_str(&format!(
+                "{} setrgbcolor /Helvetica findfont {} scalefont setfont {} {} moveto ({}) show\n",
+                rgb(colour),
+                number(*size),
+                number(at.0),
+                number(at.1),
+                escape(text)
+            )),
+        }
+    }
+    eps.push_str("showpage\n%%EOF\n");
+    eps
+}
+
+fn path_operators(path: &[Segment]) -> String {
+    let mut operators = String::new();
+    for segment in path {
+        let operator = match segment {
+            Segment::Move((x, y)) => format!("{} {} moveto ", number(*x), number(*y)),
+            Segment::Line((x, y)) => format!("{} {} lineto ", number(*x), number(*y)),
+            Segment::Curve(first, second, end) => format!(
+                "{} {} {} {} {} {} curveto ",
+                number(first.0),
+                number(first.1),
+                number(second.0),
+                number(second.1),
+                number(end.0),
+                number(end.1)
+            ),
+        };
+        operators.push_str(&operator);
+    }
+    operators
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::page::PageRect;
+    use unsvg::COLORS;
+
+    #[test]
+    fn eps_document() {
+        let page = Page {
+            size: PageSize::from_mm(210.0, 297.0),
+            canvas: PageRect {
+                x: 0.0,
+                y: 100.0,
+                width: 596.0,
+                height: 642.0,
+            },
+            shapes: vec![Shape::Stroke {
+                path: vec![
+                    Segment::Move((0.0, 0.0)),
+                    Segment::Curve((1.0, 2.0), (3.0, 4.0), (5.0, 6.0)),
+                ],
+                colour: COLORS[3],
+                width: 0.5,
+            }],
+        };
+        let eps = to_eps(&page);
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n%%BoundingBox: 0 0 596 842\n"));
+        assert!(eps.contains("%%EndComments\n0 100 596 642 rectclip\n"));
+        assert!(eps.contains(
+            "0 1 0 setrgbcolor 0.5 setlinewidth newpath 0 0 moveto 1 2 3 4 5 6 curveto stroke\n"
+        ));
+        assert!(eps.ends_with("showpage\n%%EOF\n"));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::canvas::{Canvas, Viewport};
+use crate::display::DisplayList;
+use crate::location::Location;
+use crate::render::number;
+use crate::render::plot::{order_strokes, strokes, Point};
+
+/// How the plotter lifts its pen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PenLift {
+    /// Moves the Z axis to these heights in millimetres.
+    Z { up: f64, down: f64 },
+    /// Turns a servo to these angles with `M280`.
+    Servo { up: f64, down: f64 },
+}
+
+/// Where the machine's 0, 0 is on the drawing.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Origin {
+    /// Where the turtle started.
+    Turtle,
+    /// The middle of the canvas.
+    Centre,
+    /// The bottom left corner of the canvas, so every coordinate is positive.
+    BottomLeft,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GcodeOptions {
+    pub pen: PenLift,
+    /// Drawing speed in millimetres a minute.
+    pub draw_feed: f64,
+    /// Pen up speed in millimetres a minute.
+    pub travel_feed: f64,
+    /// Millimetres per turtle unit.
+    pub scale: f64,
+    pub origin: Origin,
+    /// Reorder strokes to cut down on pen up travel.
+    pub optimise: bool,
+}
+
+/// Writes `drawing` as g-code for a pen plotter or laser cutter.
+pub fn save_gcode<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    options: &GcodeOptions,
+    path: P,
+) -> Result<(), String> {
+    let gcode = to_gcode(drawing, &canvas.viewport(drawing), options);
+    std::fs::write(path, gcode).map_err(|e| e.to_string())
+}
+
+pub fn to_gcode(drawing: &DisplayList, viewport: &Viewport, options: &GcodeOptions) -> String {
+    // Where the machine's 0, 0 is on the image, with y flipped to point up.
+    let (width, height) = (viewport.width as f64, viewport.height as f64);
+    let home = match options.origin {
+        Origin::Turtle => viewport.to_image(&Location::origin()),
+        Origin::Centre => (width / 2f64, height / 2f64),
+        Origin::BottomLeft => (0f64, height),
+    };
+    let machine = |(x, y): Point| {
+        (
+            number((x - home.0) * options.scale),
+            number((home.1 - y) * options.scale),
+        )
+    };
+
+    let mut strokes = strokes(drawing, viewport);
+    if options.optimise {
+        strokes = order_strokes(strokes, home);
+    }
+
+    let mut gcode = String::from("; rslogo\nG21 ; millimetres\nG90 ; absolute positioning\n");
+    gcode.push_str(&pen_up(&options.pen));
+    for stroke in &strokes {
+        let (x, y) = machine(stroke.points[0]);
+        gcode.push_str(&format!("G0 X{x} Y{y} F{}\n", number(options.travel_feed)));
+        gcode.push_str(&pen_down(&options.pen));
+        for point in &stroke.points[1..] {
+            let (x, y) = machine(*point);
+            gcode.push_str(&format!("G1 X{x} Y{y} F{}\n", number(options.draw_feed)));
+        }
+        gcode.push_str(&pen_up(&options.pen));
+    }
+    gcode.push_str(&format!("G0 X0 Y0 F{}\nM2\n", number(options.travel_feed)));
+    gcode
+}
+
+fn pen_up(pen: &PenLift) -> String {
+    match pen {
+        PenLift::Z { up, .. } => format!("G0 Z{}\n", number(*up)),
+        PenLift::Servo { up, .. } => format!("M280 P0 S{}\nG4 P150\n", number(*up)),
+    }
+}
+
+fn pen_down(pen: &PenLift) -> String {
+    match pen {
+        PenLift::Z { down, .. } => format!("G0 Z{}\n", number(*down)),
+        PenLift::Servo { down, .. } => format!("M280 P0 S{}\nG4 P150\n", number(*down)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::fixtures::style;
+    use crate::display::DrawCommand;
+
+    fn options() -> GcodeOptions {
+        GcodeOptions {
+            pen: PenLift::Z { up: 5.0, down: 0.0 },
+            draw_feed: 1000.0,
+            travel_feed: 3000.0,
+            scale: 0.5,
+            origin: Origin::Turtle,
+            optimise: false,
+        }
+    }
+
+    fn drawing() -> DisplayList {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Line {
+            start: Location::new(10.0, 0.0),
+            end: Location::new(10.0, 20.0),
+            style: style(1),
+        });
+        drawing
+    }
+
+    fn viewport() -> Viewport {
+        Canvas::new(100, 100).viewport(&DisplayList::new())
+    }
+
+    #[test]
+    fn turtle_origin_and_scale() {
+        let gcode = to_gcode(&drawing(), &viewport(), &options());
+        assert_eq!(
+            gcode,
+            "; rslogo\nG21 ; millimetres\nG90 ; absolute positioning\nG0 Z5\nG0 X5 Y0 F3000\nG0 Z0\nG1 X5 Y10 F1000\nG0 Z5\nG0 X0 Y0 F3000\nM2\n"
+        );
+    }
+
+    #[test]
+    fn bottom_left_origin() {
+        let options = GcodeOptions {
+            origin: Origin::BottomLeft,
+            scale: 1.0,
+            ..options()
+        };
+        let gcode = to_gcode(&drawing(), &viewport(), &options);
+        assert!(gcode.contains("G0 X60 Y50 F3000\n"));
+        assert!(gcode.contains("G1 X60 Y70 F1000\n"));
+    }
+
+    #[test]
+    fn servo_pen() {
+        let options = GcodeOptions {
+            pen: PenLift::Servo {
+                up: 50.0,
+                down: 30.0,
+            },
+            ..options()
+        };
+        let gcode = to_gcode(&drawing(), &viewport(), &options);
+        assert!(gcode.contains("M280 P0 S30\nG4 P150\nG1"));
+        assert!(!gcode.contains('Z'));
+    }
+}
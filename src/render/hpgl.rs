@@ -0,0 +1,147 @@
+use crate::canvas::{Canvas, Viewport};
+use crate::display::DisplayList;
+use crate::render::plot::{order_strokes, strokes, Point};
+use unsvg::{Color, COLORS};
+
+/// HPGL plotter units in a millimetre.
+const UNITS_PER_MM: f64 = 40f64;
+
+/// Writes `drawing` as HPGL for a plotter or vinyl cutter. `scale` is millimetres per turtle
+/// unit.
+pub fn save_hpgl<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    scale: f64,
+    optimise: bool,
+    path: P,
+) -> Result<(), String> {
+    let hpgl = to_hpgl(drawing, &canvas.viewport(drawing), scale, optimise);
+    std::fs::write(path, hpgl).map_err(|e| e.to_string())
+}
+
+/// The plotter's origin is the bottom left corner of the canvas, with y pointing up. Each
+/// colour gets its own pen: colour index `n` is pen `n + 1`, since `SP0` puts the pen away.
+pub fn to_hpgl(drawing: &DisplayList, viewport: &Viewport, scale: f64, optimise: bool) -> String {
+    let height = viewport.height as f64;
+    let units = |(x, y): Point| {
+        let units = |value: f64| (value * scale * UNITS_PER_MM).round() as i64;
+        format!("{},{}", units(x), units(height - y))
+    };
+
+    let mut strokes = strokes(drawing, viewport);
+    if optimise {
+        strokes = order_strokes(strokes, (0f64, height));
+    }
+
+    let mut hpgl = String::from("IN;");
+    let mut pen = None;
+    for stroke in &strokes {
+        let number = pen_number(&stroke.colour);
+        if pen != Some(number) {
+            hpgl.push_str(&format!("SP{number};"));
+            pen = Some(number);
+        }
+        hpgl.push_str(&format!("PU{};", units(stroke.points[0])));
+        let points: Vec<String> = stroke.points[1..]
+            .iter()
+            .map(|point| units(*point))
+            .collect();
+        hpgl.push_str(&format!("PD{};", points.join(",")));
+    }
+    hpgl.push_str("PU;SP0;\n");
+    hpgl
+}
+
+/// The pen holding `colour`, or pen 1 for a colour outside the palette.
+fn pen_number(colour: &Color) -> usize {
+    COLORS
+        .iter()
+        .position(|candidate| candidate == colour)
+        .map_or(1, |index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::fixtures::line;
+    use crate::interpreter;
+    use crate::turtle::Turtle;
+    use crate::turtles::Turtles;
+
+    fn viewport() -> Viewport {
+        Canvas::new(200, 200).viewport(&DisplayList::new())
+    }
+
+    #[test]
+    fn empty_drawing() {
+        assert_eq!(
+            to_hpgl(&DisplayList::new(), &viewport(), 1.0, true),
+            "IN;PU;SP0;\n"
+        );
+    }
+
+    /// Runs a program from logo_examples/ on a fresh turtle, the way main does.
+    fn run_example(path: &std::path::Path) -> Result<DisplayList, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut turtles = Turtles::new(Turtle::new());
+        let mut drawing = DisplayList::new();
+        interpreter::run(&source, &mut turtles, &mut drawing)?;
+        Ok(drawing)
+    }
+
+    #[test]
+    fn combo_example() {
+        // logo_examples/1_07_combo.lg draws a 50 unit square up and to the left of the
+        // start, in the default white pen.
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("logo_examples")
+            .join("1_07_combo.lg");
+        let drawing = run_example(&path).unwrap();
+        assert_eq!(
+            to_hpgl(&drawing, &viewport(), 1.0, false),
+            "IN;SP8;PU4000,4000;PD4000,6000,2000,6000,2000,4000,4000,4000;PU;SP0;\n"
+        );
+    }
+
+    #[test]
+    fn logo_examples() {
+        // Every example that should run is compared with the HPGL kept for it in
+        // logo_examples/hpgl/, and every one named _err must fail.
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("logo_examples");
+        let mut paths: Vec<_> = std::fs::read_dir(&examples)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("lg"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            let result = run_example(&path);
+            if name.ends_with("_err") {
+                assert!(result.is_err(), "{name} should fail");
+                continue;
+            }
+            let drawing = result.unwrap_or_else(|e| panic!("{name} failed: {e}"));
+            let expected = examples.join("hpgl").join(format!("{name}.hpgl"));
+            let expected = std::fs::read_to_string(&expected)
+                .unwrap_or_else(|e| panic!("no HPGL for {name}: {e}"));
+            assert_eq!(
+                to_hpgl(&drawing, &viewport(), 1.0, false),
+                expected,
+                "{name} drew something else"
+            );
+        }
+    }
+
+    #[test]
+    fn pen_changes_with_colour() {
+        let mut drawing = DisplayList::new();
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 1));
+        drawing.push(line((0.0, 10.0), (10.0, 10.0), 2));
+        assert_eq!(
+            to_hpgl(&drawing, &viewport(), 0.5, false),
+            "IN;SP2;PU2000,2000;PD2000,2200;SP3;PU2000,2200;PD2200,2200;PU;SP0;\n"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Renderers that turn a finished [`DisplayList`](crate::display::DisplayList) into an image.
+pub mod animation;
+pub mod eps;
+pub mod gcode;
+pub mod hpgl;
+pub mod page;
+pub mod pdf;
+pub mod plot;
+pub mod png;
+pub mod svg;
+pub mod term;
+
+use unsvg::Color;
+
+/// A colour as an `#rrggbb` hex string.
+pub fn colour_hex(colour: &Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour.red, colour.green, colour.blue)
+}
+
+/// Formats a coordinate to at most 3 decimal places.
+pub fn number(value: f64) -> String {
+    // Adding zero turns -0 into 0.
+    format!("{}", (value * 1000f64).round() / 1000f64 + 0f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unsvg::COLORS;
+
+    #[test]
+    fn hex_colours() {
+        assert_eq!(colour_hex(&COLORS[0]), "#000000");
+        assert_eq!(colour_hex(&COLORS[12]), "#fa8072");
+    }
+
+    #[test]
+    fn numbers_are_rounded() {
+        assert_eq!(number(70.71067811865476), "70.711");
+        assert_eq!(number(-0.0001), "0");
+        assert_eq!(number(50.0), "50");
+    }
+}
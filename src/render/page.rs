@@ -0,0 +1,430 @@
+use crate::canvas::{Canvas, Viewport};
+use crate::display::{DisplayList, DrawCommand, Ink, Style};
+use unsvg::Color;
+
+/// Points in a millimetre and an inch.
+const POINTS_PER_MM: f64 = 72f64 / 25.4;
+const POINTS_PER_INCH: f64 = 72f64;
+
+/// The size of a printed page in points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl std::str::FromStr for PageSize {
+    type Err = String;
+
+    /// Reads `A4`, `Letter` or a size like `210x297mm` or `8.5x11in`.
+    fn from_str(page: &str) -> Result<Self, Self::Err> {
+        match page.to_ascii_lowercase().as_str() {
+            "a4" => return Ok(PageSize::from_mm(210f64, 297f64)),
+            "letter" => return Ok(PageSize::from_inches(8.5, 11f64)),
+            _ => {}
+        }
+        let error = || format!("'{page}' isn't A4, Letter or a size like 210x297mm or 8.5x11in");
+        let lower = page.to_ascii_lowercase();
+        let (size, unit) = if let Some(size) = lower.strip_suffix("mm") {
+            (size, POINTS_PER_MM)
+        } else if let Some(size) = lower.strip_suffix("in") {
+            (size, POINTS_PER_INCH)
+        } else {
+            return Err(error());
+        };
+        let (width, height) = size.split_once('x').ok_or_else(error)?;
+        let width: f64 = width.trim().parse().map_err(|_| error())?;
+        let height: f64 = height.trim().parse().map_err(|_| error())?;
+        if !(width > 0f64 && height > 0f64 && width.is_finite() && height.is_finite()) {
+            return Err(error());
+        }
+        Ok(PageSize {
+            width: width * unit,
+            height: height * unit,
+        })
+    }
+}
+
+impl PageSize {
+    pub fn from_mm(width: f64, height: f64) -> Self {
+        PageSize {
+            width: width * POINTS_PER_MM,
+            height: height * POINTS_PER_MM,
+        }
+    }
+
+    pub fn from_inches(width: f64, height: f64) -> Self {
+        PageSize {
+            width: width * POINTS_PER_INCH,
+            height: height * POINTS_PER_INCH,
+        }
+    }
+}
+
+/// A point on the page in points, with y pointing up as in pdf and postscript.
+pub type PagePoint = (f64, f64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Move(PagePoint),
+    Line(PagePoint),
+    Curve(PagePoint, PagePoint, PagePoint),
+}
+
+/// Something to paint on a page, already scaled and placed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Stroke {
+        path: Vec<Segment>,
+        colour: Color,
+        width: f64,
+    },
+    Fill {
+        path: Vec<Segment>,
+        colour: Color,
+    },
+    Text {
+        at: PagePoint,
+        size: f64,
+        text: String,
+        colour: Color,
+    },
+}
+
+/// A rectangle on the page in points, from its bottom left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A drawing laid out on a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub size: PageSize,
+    /// Where the canvas is on the page. Shapes are clipped to it so nothing spills into
+    /// the margin around it.
+    pub canvas: PageRect,
+    pub shapes: Vec<Shape>,
+}
+
+/// Lays `drawing` out on a page. Without a page size each pixel of the canvas becomes a
+/// point; with one the canvas is scaled to fit and centred.
+pub fn layout(drawing: &DisplayList, canvas: &Canvas, page: Option<PageSize>) -> Page {
+    let viewport = canvas.viewport(drawing);
+    let (canvas_width, canvas_height) = (
+        (viewport.width as f64).max(1f64),
+        (viewport.height as f64).max(1f64),
+    );
+    let size = page.unwrap_or(PageSize {
+        width: canvas_width,
+        height: canvas_height,
+    });
+    let scale = (size.width / canvas_width).min(size.height / canvas_height);
+    let offset = (
+        (size.width - canvas_width * scale) / 2f64,
+        (size.height - canvas_height * scale) / 2f64,
+    );
+    let place = Placement {
+        viewport,
+        scale,
+        offset,
+        page_height: size.height,
+        background: drawing.background(),
+    };
+
+    let mut shapes = vec![Shape::Fill {
+        path: vec![
+            Segment::Move(place.point((0f64, 0f64))),
+            Segment::Line(place.point((canvas_width, 0f64))),
+            Segment::Line(place.point((canvas_width, canvas_height))),
+            Segment::Line(place.point((0f64, canvas_height))),
+        ],
+        colour: drawing.background(),
+    }];
+    shapes.extend(drawing.painted().filter_map(|command| place.shape(command)));
+    let (x, y) = place.point((0f64, canvas_height));
+    Page {
+        size,
+        canvas: PageRect {
+            x,
+            y,
+            width: canvas_width * scale,
+            height: canvas_height * scale,
+        },
+        shapes,
+    }
+}
+
+/// Maps image coordinates on the canvas onto the page.
+struct Placement {
+    viewport: Viewport,
+    scale: f64,
+    offset: (f64, f64),
+    page_height: f64,
+    background: Color,
+}
+
+impl Placement {
+    fn point(&self, (x, y): (f64, f64)) -> PagePoint {
+        (
+            self.offset.0 + self.scale * x,
+            self.page_height - (self.offset.1 + self.scale * y),
+        )
+    }
+
+    /// Pdf and eps can't invert what is underneath, so reversed strokes are drawn in the
+    /// pen colour. Erased strokes paint the background back.
+    fn stroke_colour(&self, style: &Style) -> Color {
+        match style.ink {
+            Ink::Erase => self.background,
+            Ink::Paint | Ink::Reverse => style.colour,
+        }
+    }
+
+    fn shape(&self, command: &DrawCommand) -> Option<Shape> {
+        let viewport = &self.viewport;
+        match command {
+            DrawCommand::Line { start, end, style } => {
+                let (start, end) =
+                    viewport.clip(viewport.to_image(start), viewport.to_image(end))?;
+                Some(Shape::Stroke {
+                    path: vec![
+                        Segment::Move(self.point(start)),
+                        Segment::Line(self.point(end)),
+                    ],
+                    colour: self.stroke_colour(style),
+                    width: style.width * self.scale,
+                })
+            }
+            DrawCommand::Arc {
+                centre,
+                radius,
+                start,
+                sweep,
+                style,
+            } => {
+                let centre = viewport.to_image(centre);
+                Some(Shape::Stroke {
+                    path: self.arc(centre, *radius, *start, sweep.clamp(-360f64, 360f64)),
+                    colour: self.stroke_colour(style),
+                    width: style.width * self.scale,
+                })
+            }
+            DrawCommand::Fill { points, colour } => {
+                let mut path: Vec<Segment> = points
+                    .iter()
+                    .map(|point| Segment::Line(self.point(viewport.to_image(point))))
+                    .collect();
+                if let Some(Segment::Line(first)) = path.first() {
+                    path[0] = Segment::Move(*first);
+                }
+                Some(Shape::Fill {
+                    path,
+                    colour: *colour,
+                })
+            }
+            DrawCommand::Text {
+                position,
+                text,
+                style,
+            } => Some(Shape::Text {
+                at: self.point(viewport.to_image(position)),
+                size: 12f64 * style.width * self.scale,
+                text: text.clone(),
+                colour: style.colour,
+            }),
+        }
+    }
+
+    /// An arc as bezier curves of at most 90 degrees each, since neither pdf nor eps can
+    /// draw an arc directly the same way.
+    fn arc(&self, centre: (f64, f64), radius: f64, start: f64, sweep: f64) -> Vec<Segment> {
+        // On the image a compass heading points to (sin, -cos) and the curve at that point
+        // heads along (cos, sin).
+        let point = |heading: f64| {
+            (
+                centre.0 + radius * heading.sin(),
+                centre.1 - radius * heading.cos(),
+            )
+        };
+        let tangent = |heading: f64| (heading.cos(), heading.sin());
+        let pieces = (sweep.abs() / 90f64).ceil().max(1f64) as usize;
+        let step = sweep.to_radians() / pieces as f64;
+        let handle = 4f64 / 3f64 * (step / 4f64).tan() * radius;
+
+        let mut heading = start.to_radians();
+        let mut path = vec![Segment::Move(self.point(point(heading)))];
+        for _ in 0..pieces {
+            let next = heading + step;
+            let (from, to) = (point(heading), point(next));
+            let (from_tangent, to_tangent) = (tangent(heading), tangent(next));
+            path.push(Segment::Curve(
+                self.point((
+                    from.0 + handle * from_tangent.0,
+                    from.1 + handle * from_tangent.1,
+                )),
+                self.point((to.0 - handle * to_tangent.0, to.1 - handle * to_tangent.1)),
+                self.point(to),
+            ));
+            heading = next;
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::fixtures::style;
+    use crate::display::{Ink, Style};
+    use crate::location::Location;
+    use unsvg::COLORS;
+
+    #[test]
+    fn named_pages() {
+        let a4: PageSize = "A4".parse().unwrap();
+        let letter: PageSize = "letter".parse().unwrap();
+        assert!((a4.width - 595.276).abs() < 0.001);
+        assert!((a4.height - 841.89).abs() < 0.001);
+        assert_eq!(letter, PageSize::from_inches(8.5, 11.0));
+    }
+
+    #[test]
+    fn custom_pages() {
+        let page: PageSize = "100x50mm".parse().unwrap();
+        assert_eq!(page, PageSize::from_mm(100.0, 50.0));
+        let page: PageSize = "4x6in".parse().unwrap();
+        assert_eq!(page, PageSize::from_inches(4.0, 6.0));
+    }
+
+    #[test]
+    fn bad_pages() {
+        assert!("A5".parse::<PageSize>().is_err());
+        assert!("10x10cm".parse::<PageSize>().is_err());
+        assert!("0x10mm".parse::<PageSize>().is_err());
+        assert!("10mm".parse::<PageSize>().is_err());
+    }
+
+    #[test]
+    fn pixels_become_points() {
+        let page = layout(&DisplayList::new(), &Canvas::new(100, 50), None);
+        assert_eq!(
+            page.size,
+            PageSize {
+                width: 100.0,
+                height: 50.0
+            }
+        );
+        assert_eq!(page.shapes.len(), 1);
+    }
+
+    #[test]
+    fn scaled_to_fit_page() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 50.0),
+            style: style(1),
+        });
+        let page = PageSize {
+            width: 200.0,
+            height: 400.0,
+        };
+        let page = layout(&drawing, &Canvas::new(100, 100), Some(page));
+        // Scaled by 2 and centred vertically, with y flipped to point up.
+        assert_eq!(
+            page.shapes[1],
+            Shape::Stroke {
+                path: vec![Segment::Move((100.0, 200.0)), Segment::Line((100.0, 300.0))],
+                colour: COLORS[1],
+                width: 2.0,
+            }
+        );
+        // The margins above and below the canvas are clipped off.
+        assert_eq!(
+            page.canvas,
+            PageRect {
+                x: 0.0,
+                y: 100.0,
+                width: 200.0,
+                height: 200.0
+            }
+        );
+    }
+
+    #[test]
+    fn empty_canvas_scale_is_finite() {
+        let page = layout(
+            &DisplayList::new(),
+            &Canvas::new(0, 0),
+            Some(PageSize::from_mm(210.0, 297.0)),
+        );
+        assert!(page.canvas.width.is_finite() && page.canvas.height.is_finite());
+        match &page.shapes[0] {
+            Shape::Fill { path, .. } => assert!(path.iter().all(|segment| match segment {
+                Segment::Move((x, y)) | Segment::Line((x, y)) => x.is_finite() && y.is_finite(),
+                Segment::Curve(..) => false,
+            })),
+            _ => panic!("expected the background"),
+        }
+    }
+
+    #[test]
+    fn arc_is_split_into_quarters() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Arc {
+            centre: Location::origin(),
+            radius: 10.0,
+            start: 0.0,
+            sweep: 180.0,
+            style: style(1),
+        });
+        let page = layout(&drawing, &Canvas::new(100, 100), None);
+        match &page.shapes[1] {
+            Shape::Stroke { path, .. } => {
+                assert_eq!(path.len(), 3);
+                match path[2] {
+                    Segment::Curve(_, _, (x, y)) => {
+                        assert!((x - 50.0).abs() < 1e-9);
+                        assert!((y - 40.0).abs() < 1e-9);
+                    }
+                    _ => panic!("expected a curve"),
+                }
+            }
+            _ => panic!("expected a stroke"),
+        }
+    }
+
+    #[test]
+    fn background_fills_canvas() {
+        let mut drawing = DisplayList::new();
+        drawing.set_background(COLORS[2]);
+        let page = layout(&drawing, &Canvas::new(10, 10), None);
+        match &page.shapes[0] {
+            Shape::Fill { colour, .. } => assert_eq!(*colour, COLORS[2]),
+            _ => panic!("expected a fill"),
+        }
+    }
+
+    #[test]
+    fn erase_strokes_in_background() {
+        let mut drawing = DisplayList::new();
+        drawing.set_background(COLORS[5]);
+        drawing.push(DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 10.0),
+            style: Style {
+                ink: Ink::Erase,
+                ..style(1)
+            },
+        });
+        let page = layout(&drawing, &Canvas::new(100, 100), None);
+        match &page.shapes[1] {
+            Shape::Stroke { colour, .. } => assert_eq!(*colour, COLORS[5]),
+            _ => panic!("expected a stroke"),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::canvas::Canvas;
+use crate::display::DisplayList;
+use crate::render::number;
+use crate::render::page::{layout, Page, PageSize, Segment, Shape};
+use unsvg::Color;
+
+/// Writes `drawing` to a single page pdf.
+pub fn save_pdf<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    page: Option<PageSize>,
+    path: P,
+) -> Result<(), String> {
+    let pdf = to_pdf(&layout(drawing, canvas, page));
+    std::fs::write(path, pdf).map_err(|e| e.to_string())
+}
+
+/// Builds a pdf document holding `page`.
+pub fn to_pdf(page: &Page) -> Vec<u8> {
+    let content = content(page);
+    let objects = [
+        String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+        String::from("<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>",
+            number(page.size.width),
+            number(page.size.height)
+        ),
+        format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ),
+        String::from("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>"),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", index + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{offset:010} 00000 n \n"));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    pdf.into_bytes()
+}
+
+/// The page's content stream.
+fn content(page: &Page) -> String {
+    let canvas = &page.canvas;
+    let mut content = format!(
+        "{} {} {} {} re W n\n",
+        number(canvas.x),
+        number(canvas.y),
+        number(canvas.width),
+        number(canvas.height)
+    );
+    for shape in &page.shapes {
+        match shape {
+            Shape::Stroke {
+                path,
+                colour,
+                width,
+            } => {
+                content.push_str(&format!("{} RG {} w ", rgb(colour), number(*width)));
+                content.push_str(&path_operators(path));
+                content.push_str("S\n");
+            }
+            Shape::Fill { path, colour } => {
+                content.push_str(&format!("{} rg ", rgb(colour)));
+                content.push_str(&path_operators(path));
+                content.push_str("h f\n");
+            }
+            Shape::Text {
+                at,
+                size,
+                text,
+                colour,
+            } => content.push_str(&format!(
+                "BT {} rg /F1 {} Tf {} {} Td ({}) Tj ET\n",
+                rgb(colour),
+                number(*size),
+                number(at.0),
+                number(at.1),
+                escape(text)
+            )),
+        }
+    }
+    content
+}
+
+fn path_operators(path: &[Segment]) -> String {
+    let mut operators = String::new();
+    for segment in path {
+        let operator = match segment {
+            Segment::Move((x, y)) => format!("{} {} m ", number(*x), number(*y)),
+            Segment::Line((x, y)) => format!("{} {} l ", number(*x), number(*y)),
+            Segment::Curve(first, second, end) => format!(
+                "{} {} {} {} {} {} c ",
+                number(first.0),
+                number(first.1),
+                number(second.0),
+                number(second.1),
+                number(end.0),
+                number(end.1)
+            ),
+        };
+        operators.push_str(&operator);
+    }
+    operators
+}
+
+/// A colour as pdf's red, green and blue fractions.
+pub fn rgb(colour: &Color) -> String {
+    format!(
+        "{} {} {}",
+        number(colour.red as f64 / 255f64),
+        number(colour.green as f64 / 255f64),
+        number(colour.blue as f64 / 255f64)
+    )
+}
+
+/// Escapes text for a pdf or postscript string literal.
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::page::PageRect;
+    use unsvg::COLORS;
+
+    fn page() -> Page {
+        Page {
+            size: PageSize {
+                width: 100.0,
+                height: 50.0,
+            },
+            canvas: PageRect {
+                x: 10.0,
+                y: 0.0,
+                width: 80.0,
+                height: 50.0,
+            },
+            shapes: vec![
+                Shape::Stroke {
+                    path: vec![Segment::Move((0.0, 0.0)), Segment::Line((10.0, 5.5))],
+                    colour: COLORS[4],
+                    width: 2.0,
+                },
+                Shape::Text {
+                    at: (1.0, 2.0),
+                    size: 12.0,
+                    text: String::from("a (b)"),
+                    colour: COLORS[7],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn stroke_keeps_colour_and_width() {
+        let content = content(&page());
+        assert!(content.starts_with("10 0 80 50 re W n\n1 0 0 RG 2 w 0 0 m 10 5.5 l S\n"));
+        assert!(content.contains("(a \\(b\\)) Tj"));
+    }
+
+    #[test]
+    fn document_structure() {
+        let pdf = String::from_utf8(to_pdf(&page())).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.contains("/MediaBox [0 0 100 50]"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        // The cross reference table points at each object.
+        let xref = pdf.find("xref\n").unwrap();
+        let offset = pdf[xref..].lines().nth(5).unwrap()[..10]
+            .parse::<usize>()
+            .unwrap();
+        assert!(pdf[offset..].starts_with("3 0 obj"));
+    }
+}
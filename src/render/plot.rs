@@ -0,0 +1,243 @@
+use crate::canvas::Viewport;
+use crate::display::{DisplayList, DrawCommand, Ink};
+use unsvg::Color;
+
+/// A point on the image.
+pub type Point = (f64, f64);
+
+/// One pen down stretch for a plotter: the pen goes down at the first point and comes up
+/// after the last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub points: Vec<Point>,
+    pub colour: Color,
+}
+
+/// Turns `drawing` into plotter strokes in image coordinates. Lines that carry on from the
+/// previous one in the same colour join onto its stroke, arcs are flattened to short lines,
+/// fills are outlined and text is left out since a plotter has no font to draw it with.
+/// Erased lines are left out too, since a pen can't take ink back off, and so are guides.
+pub fn strokes(drawing: &DisplayList, viewport: &Viewport) -> Vec<Stroke> {
+    let mut strokes: Vec<Stroke> = Vec::new();
+    for command in drawing.commands() {
+        let (points, colour) = match command {
+            DrawCommand::Line { style, .. } | DrawCommand::Arc { style, .. }
+                if style.ink == Ink::Erase =>
+            {
+                continue
+            }
+            DrawCommand::Line { start, end, style } => {
+                match viewport.clip(viewport.to_image(start), viewport.to_image(end)) {
+                    Some((start, end)) => (vec![start, end], style.colour),
+                    None => continue,
+                }
+            }
+            DrawCommand::Arc {
+                centre,
+                radius,
+                start,
+                sweep,
+                style,
+            } => {
+                let centre = viewport.to_image(centre);
+                let sweep = sweep.clamp(-360f64, 360f64);
+                let pieces = (sweep.abs() / 5f64).ceil().max(1f64) as usize;
+                let points = (0..=pieces)
+                    .map(|piece| {
+                        let heading = (start + sweep * piece as f64 / pieces as f64).to_radians();
+                        (
+                            centre.0 + radius * heading.sin(),
+                            centre.1 - radius * heading.cos(),
+                        )
+                    })
+                    .collect();
+                (points, style.colour)
+            }
+            DrawCommand::Fill { points, colour } => {
+                let mut points: Vec<Point> = points
+                    .iter()
+                    .map(|point| viewport.to_image(point))
+                    .collect();
+                if let Some(first) = points.first().copied() {
+                    points.push(first);
+                }
+                (points, *colour)
+            }
+            DrawCommand::Text { .. } => continue,
+        };
+        match strokes.last_mut() {
+            Some(last) if last.colour == colour && last.points.last() == points.first() => {
+                last.points.extend_from_slice(&points[1..]);
+            }
+            _ => strokes.push(Stroke { points, colour }),
+        }
+    }
+    strokes
+}
+
+/// Reorders strokes to cut down on pen up travel. Starting from `from`, it repeatedly
+/// draws whichever remaining stroke has an end nearest the pen, reversing it if that end is
+/// its last point. Strokes keep their colour order, so each pen is only picked up once.
+pub fn order_strokes(strokes: Vec<Stroke>, from: Point) -> Vec<Stroke> {
+    let mut colours: Vec<Color> = Vec::new();
+    for stroke in &strokes {
+        if !colours.contains(&stroke.colour) {
+            colours.push(stroke.colour);
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(strokes.len());
+    let mut position = from;
+    for colour in colours {
+        let mut remaining: Vec<Stroke> = strokes
+            .iter()
+            .filter(|stroke| stroke.colour == colour)
+            .cloned()
+            .collect();
+        while !remaining.is_empty() {
+            let mut best = (0, false, f64::INFINITY);
+            for (index, stroke) in remaining.iter().enumerate() {
+                let first = distance(position, stroke.points[0]);
+                let last = distance(position, stroke.points[stroke.points.len() - 1]);
+                if first < best.2 {
+                    best = (index, false, first);
+                }
+                if last < best.2 {
+                    best = (index, true, last);
+                }
+            }
+            let mut stroke = remaining.swap_remove(best.0);
+            if best.1 {
+                stroke.points.reverse();
+            }
+            position = stroke.points[stroke.points.len() - 1];
+            ordered.push(stroke);
+        }
+    }
+    ordered
+}
+
+/// How far the pen travels with the pen up to draw `strokes` in order, starting at `from`.
+pub fn travel(strokes: &[Stroke], from: Point) -> f64 {
+    let mut position = from;
+    let mut total = 0f64;
+    for stroke in strokes {
+        total += distance(position, stroke.points[0]);
+        position = stroke.points[stroke.points.len() - 1];
+    }
+    total
+}
+
+fn distance(from: Point, to: Point) -> f64 {
+    (to.0 - from.0).hypot(to.1 - from.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Canvas;
+    use crate::display::fixtures::{line, style};
+    use crate::display::{Ink, Style};
+    use crate::location::Location;
+    use unsvg::COLORS;
+
+    fn viewport() -> Viewport {
+        Canvas::new(100, 100).viewport(&DisplayList::new())
+    }
+
+    #[test]
+    fn joined_lines_make_one_stroke() {
+        let mut drawing = DisplayList::new();
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 1));
+        drawing.push(line((0.0, 10.0), (10.0, 10.0), 1));
+        drawing.push(line((10.0, 10.0), (10.0, 0.0), 2));
+        let strokes = strokes(&drawing, &viewport());
+        assert_eq!(strokes.len(), 2);
+        assert_eq!(
+            strokes[0].points,
+            vec![(50.0, 50.0), (50.0, 40.0), (60.0, 40.0)]
+        );
+    }
+
+    #[test]
+    fn text_is_left_out() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Text {
+            position: Location::origin(),
+            text: String::from("hi"),
+            style: style(1),
+        });
+        assert!(strokes(&drawing, &viewport()).is_empty());
+    }
+
+    #[test]
+    fn arc_is_flattened() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Arc {
+            centre: Location::origin(),
+            radius: 10.0,
+            start: 0.0,
+            sweep: 90.0,
+            style: style(1),
+        });
+        let strokes = strokes(&drawing, &viewport());
+        assert_eq!(strokes[0].points.len(), 19);
+        let end = strokes[0].points[18];
+        assert!((end.0 - 60.0).abs() < 1e-9 && (end.1 - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ordering_cuts_travel() {
+        let stroke = |from: Point, to: Point| Stroke {
+            points: vec![from, to],
+            colour: COLORS[1],
+        };
+        let strokes = vec![
+            stroke((90.0, 0.0), (100.0, 0.0)),
+            stroke((20.0, 0.0), (10.0, 0.0)),
+            stroke((50.0, 0.0), (60.0, 0.0)),
+        ];
+        let before = travel(&strokes, (0.0, 0.0));
+        let ordered = order_strokes(strokes, (0.0, 0.0));
+        assert!(travel(&ordered, (0.0, 0.0)) < before);
+        assert_eq!(ordered[0].points, vec![(10.0, 0.0), (20.0, 0.0)]);
+        assert_eq!(ordered[2].points, vec![(90.0, 0.0), (100.0, 0.0)]);
+    }
+
+    #[test]
+    fn ordering_keeps_colours_together() {
+        let stroke = |x: f64, colour: usize| Stroke {
+            points: vec![(x, 0.0), (x + 1.0, 0.0)],
+            colour: COLORS[colour],
+        };
+        let strokes = vec![stroke(0.0, 1), stroke(10.0, 2), stroke(20.0, 1)];
+        let ordered = order_strokes(strokes, (0.0, 0.0));
+        let colours: Vec<Color> = ordered.iter().map(|stroke| stroke.colour).collect();
+        assert_eq!(colours, vec![COLORS[1], COLORS[1], COLORS[2]]);
+    }
+
+    #[test]
+    fn erased_lines_are_left_out() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 10.0),
+            style: Style {
+                ink: Ink::Erase,
+                ..style(1)
+            },
+        });
+        assert!(strokes(&drawing, &viewport()).is_empty());
+    }
+
+    #[test]
+    fn guides_are_left_out() {
+        let mut drawing = DisplayList::new();
+        drawing.set_guides(vec![DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 10.0),
+            style: style(15),
+        }]);
+        assert!(strokes(&drawing, &viewport()).is_empty());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::canvas::{Canvas, Viewport};
+use crate::display::{DisplayList, DrawCommand};
+use crate::render::svg::to_svg_with_background;
+use resvg::tiny_skia::{Pixmap, PremultipliedColorU8, Transform};
+use resvg::usvg::{self, TreeParsing, TreeTextToPath};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// What is behind the drawing.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Background {
+    /// The canvas colour.
+    Solid,
+    /// Nothing, so only what was drawn is opaque.
+    Transparent,
+}
+
+/// How a png is rasterised.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PngOptions {
+    /// Pixels per canvas unit.
+    pub scale: f64,
+    /// Render this many times larger in each direction and average down, for smoother
+    /// edges.
+    pub supersample: u32,
+    pub background: Background,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptions {
+            scale: 1f64,
+            supersample: 1,
+            background: Background::Solid,
+        }
+    }
+}
+
+/// Screen pixels per inch, which a scale of 1 is drawn at.
+pub const BASE_DPI: f64 = 96f64;
+
+/// Writes `drawing` to a png file, recording its resolution so it prints at the canvas
+/// size.
+pub fn save_png<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    options: &PngOptions,
+    path: P,
+) -> Result<(), String> {
+    let pixmap = rasterise_with(drawing, &canvas.viewport(drawing), options)?;
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), pixmap.width(), pixmap.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let pixels_per_metre = (BASE_DPI * options.scale / 0.0254).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: pixels_per_metre,
+        yppu: pixels_per_metre,
+        unit: png::Unit::Meter,
+    }));
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(&straight_rgba(&pixmap))
+        .map_err(|e| e.to_string())
+}
+
+/// Renders `drawing` to pixels at its canvas size on a solid background.
+pub fn rasterise(drawing: &DisplayList, viewport: &Viewport) -> Result<Pixmap, String> {
+    rasterise_with(drawing, viewport, &PngOptions::default())
+}
+
+/// How many pixels wide and high the image of `viewport` comes out at.
+pub fn image_size(viewport: &Viewport, options: &PngOptions) -> (u32, u32) {
+    let scaled = |size: u32| ((size as f64 * options.scale).round() as u32).max(1);
+    (scaled(viewport.width), scaled(viewport.height))
+}
+
+/// Renders `drawing` to pixels by rasterising its svg.
+pub fn rasterise_with(
+    drawing: &DisplayList,
+    viewport: &Viewport,
+    options: &PngOptions,
+) -> Result<Pixmap, String> {
+    let svg = to_svg_with_background(drawing, viewport, options.background == Background::Solid);
+    let mut tree =
+        usvg::Tree::from_str(&svg, &usvg::Options::default()).map_err(|e| e.to_string())?;
+    let has_text = drawing
+        .commands()
+        .iter()
+        .any(|command| matches!(command, DrawCommand::Text { .. }));
+    if has_text {
+        let mut fonts = usvg::fontdb::Database::new();
+        fonts.load_system_fonts();
+        tree.convert_text(&fonts);
+    }
+
+    let rtree = resvg::Tree::from_usvg(&tree);
+    let (width, height) = image_size(viewport, options);
+    let factor = options.supersample.max(1);
+    let (Some(full_width), Some(full_height)) =
+        (width.checked_mul(factor), height.checked_mul(factor))
+    else {
+        return Err(String::from("Could not allocate image"));
+    };
+    let mut pixmap = match Pixmap::new(full_width, full_height) {
+        Some(pixmap) => pixmap,
+        None => return Err(String::from("Could not allocate image")),
+    };
+    let transform = Transform::from_scale(
+        full_width as f32 / viewport.width.max(1) as f32,
+        full_height as f32 / viewport.height.max(1) as f32,
+    );
+    rtree.render(transform, &mut pixmap.as_mut());
+    if factor == 1 {
+        return Ok(pixmap);
+    }
+    downsample(&pixmap, factor)
+}
+
+/// Shrinks `pixmap` by `factor` in each direction, averaging each block of pixels.
+fn downsample(pixmap: &Pixmap, factor: u32) -> Result<Pixmap, String> {
+    let (width, height) = (pixmap.width() / factor, pixmap.height() / factor);
+    let mut small = match Pixmap::new(width, height) {
+        Some(small) => small,
+        None => return Err(String::from("Could not allocate image")),
+    };
+    let source = pixmap.pixels();
+    let count = factor * factor;
+    for y in 0..height {
+        for x in 0..width {
+            let mut sums = [0u32; 4];
+            for dy in 0..factor {
+                for dx in 0..factor {
+                    let index = ((y * factor + dy) * pixmap.width() + x * factor + dx) as usize;
+                    let pixel = source[index];
+                    sums[0] += pixel.red() as u32;
+                    sums[1] += pixel.green() as u32;
+                    sums[2] += pixel.blue() as u32;
+                    sums[3] += pixel.alpha() as u32;
+                }
+            }
+            let [red, green, blue, alpha] = sums.map(|sum| ((sum + count / 2) / count) as u8);
+            // Averages of premultiplied colours are still premultiplied, but rounding can
+            // leave a channel just over the alpha.
+            let pixel = PremultipliedColorU8::from_rgba(
+                red.min(alpha),
+                green.min(alpha),
+                blue.min(alpha),
+                alpha,
+            );
+            if let Some(pixel) = pixel {
+                small.pixels_mut()[(y * width + x) as usize] = pixel;
+            }
+        }
+    }
+    Ok(small)
+}
+
+/// The pixels as straight (not premultiplied) rgba bytes.
+pub fn straight_rgba(pixmap: &Pixmap) -> Vec<u8> {
+    pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let pixel = pixel.demultiply();
+            [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::fixtures::style;
+    use crate::display::{Dash, Ink, Style};
+    use crate::location::Location;
+    use unsvg::COLORS;
+
+    #[test]
+    fn draws_line_pixels() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Line {
+            start: Location::new(0.0, -10.0),
+            end: Location::new(0.0, 10.0),
+            style: Style {
+                width: 2f64,
+                ..style(7)
+            },
+        });
+        let viewport = Canvas::new(32, 32).viewport(&drawing);
+        let pixmap = rasterise(&drawing, &viewport).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (32, 32));
+        let centre = pixmap.pixel(16, 16).unwrap();
+        let corner = pixmap.pixel(0, 0).unwrap();
+        assert_eq!(
+            (centre.red(), centre.green(), centre.blue()),
+            (255, 255, 255)
+        );
+        assert_eq!((corner.red(), corner.green(), corner.blue()), (0, 0, 0));
+    }
+
+    fn diagonal() -> DisplayList {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Line {
+            start: Location::new(-10.0, -10.0),
+            end: Location::new(10.0, 10.0),
+            style: style(7),
+        });
+        drawing
+    }
+
+    #[test]
+    fn scaled_size() {
+        let viewport = Canvas::new(32, 16).viewport(&DisplayList::new());
+        let options = PngOptions {
+            scale: 2.5,
+            ..PngOptions::default()
+        };
+        let pixmap = rasterise_with(&DisplayList::new(), &viewport, &options).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (80, 40));
+    }
+
+    #[test]
+    fn transparent_background() {
+        let drawing = diagonal();
+        let viewport = Canvas::new(32, 32).viewport(&drawing);
+        let options = PngOptions {
+            background: Background::Transparent,
+            ..PngOptions::default()
+        };
+        let pixmap = rasterise_with(&drawing, &viewport, &options).unwrap();
+        assert_eq!(pixmap.pixel(0, 0).unwrap().alpha(), 0);
+        assert_eq!(pixmap.pixel(16, 15).unwrap().alpha(), 255);
+    }
+
+    #[test]
+    fn supersampling_keeps_size_and_smooths() {
+        let drawing = diagonal();
+        let viewport = Canvas::new(32, 32).viewport(&drawing);
+        let options = PngOptions {
+            supersample: 4,
+            ..PngOptions::default()
+        };
+        let pixmap = rasterise_with(&drawing, &viewport, &options).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (32, 32));
+        // Beside the diagonal is partly covered, so neither black nor white.
+        let beside = pixmap.pixel(17, 15).unwrap();
+        assert!(beside.red() > 0 && beside.red() < 255);
+    }
+
+    #[test]
+    fn saves_resolution() {
+        let path = std::env::temp_dir().join("rslogo_png_dpi_test.png");
+        let options = PngOptions {
+            scale: 3.125,
+            ..PngOptions::default()
+        };
+        let res = save_png(&DisplayList::new(), &Canvas::new(4, 4), &options, &path);
+        assert!(res.is_ok());
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        let dims = reader.info().pixel_dims.unwrap();
+        // 300 dpi is 11811 pixels a metre.
+        assert_eq!(dims.xppu, 11811);
+        assert_eq!(reader.info().width, 13);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn reverse_inverts_pixels() {
+        let mut drawing = DisplayList::new();
+        for (ink, colour) in [(Ink::Paint, 7), (Ink::Reverse, 1), (Ink::Reverse, 1)] {
+            let width = if colour == 7 { 4f64 } else { 2f64 };
+            drawing.push(DrawCommand::Line {
+                start: Location::new(-10.0, 0.0),
+                end: Location::new(10.0, 0.0),
+                style: Style {
+                    colour: COLORS[colour],
+                    width,
+                    ink,
+                    dash: None,
+                },
+            });
+            if ink == Ink::Reverse {
+                // Reversing once turns white to black and the black background white.
+                let viewport = Canvas::new(32, 32).viewport(&drawing);
+                let pixmap = rasterise(&drawing, &viewport).unwrap();
+                let on_white = pixmap.pixel(16, 15).unwrap();
+                if drawing.commands().len() == 2 {
+                    assert_eq!(on_white.red(), 0);
+                } else {
+                    // Twice puts it back.
+                    assert_eq!(on_white.red(), 255);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn erase_paints_background() {
+        let mut drawing = diagonal();
+        drawing.push(DrawCommand::Line {
+            start: Location::new(-10.0, -10.0),
+            end: Location::new(10.0, 10.0),
+            style: Style {
+                width: 3f64,
+                ink: Ink::Erase,
+                ..style(7)
+            },
+        });
+        let viewport = Canvas::new(32, 32).viewport(&drawing);
+        let pixmap = rasterise(&drawing, &viewport).unwrap();
+        assert_eq!(pixmap.pixel(16, 15).unwrap().red(), 0);
+    }
+
+    #[test]
+    fn dashes_continue_across_lines() {
+        let mut drawing = DisplayList::new();
+        // Dashes at 0-4 and 8-12 along the line, with the second line starting mid gap.
+        for (offset, start, end) in [(0.0, -6.0, 0.0), (6.0, 0.0, 6.0)] {
+            drawing.push(DrawCommand::Line {
+                start: Location::new(start, 0.0),
+                end: Location::new(end, 0.0),
+                style: Style {
+                    width: 2f64,
+                    dash: Some(Dash {
+                        pattern: vec![4.0, 4.0],
+                        offset,
+                    }),
+                    ..style(7)
+                },
+            });
+        }
+        let viewport = Canvas::new(32, 32).viewport(&drawing);
+        let pixmap = rasterise(&drawing, &viewport).unwrap();
+        let red = |x: u32| pixmap.pixel(x, 15).unwrap().red();
+        assert_eq!(red(12), 255);
+        assert_eq!(red(15), 0);
+        assert_eq!(red(17), 0);
+        assert_eq!(red(19), 255);
+    }
+
+    #[test]
+    fn oversized_supersample_errors() {
+        let options = PngOptions {
+            scale: 10000000f64,
+            supersample: 16,
+            ..PngOptions::default()
+        };
+        let drawing = diagonal();
+        let viewport = Canvas::new(32, 32).viewport(&drawing);
+        assert_eq!(
+            rasterise_with(&drawing, &viewport, &options).err(),
+            Some(String::from("Could not allocate image"))
+        );
+    }
+}
@@ -0,0 +1,841 @@
+use crate::canvas::{Canvas, Viewport};
+use crate::display::{Dash, DisplayList, DrawCommand, Ink, Style};
+use crate::location::Location;
+use crate::render::{colour_hex, number};
+use unsvg::Color;
+
+/// How fast an animated svg draws.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pace {
+    /// The whole drawing takes this many seconds.
+    Duration(f64),
+    /// The turtle covers this many units a second.
+    Speed(f64),
+}
+
+/// Writes `drawing` to an svg file.
+pub fn save_svg<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    path: P,
+) -> Result<(), String> {
+    let svg = to_svg(drawing, &canvas.viewport(drawing));
+    std::fs::write(path, svg).map_err(|e| e.to_string())
+}
+
+/// Writes `drawing` to an svg file that traces it out in the order it was drawn.
+pub fn save_animated_svg<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    pace: Pace,
+    path: P,
+) -> Result<(), String> {
+    let svg = to_animated_svg(drawing, &canvas.viewport(drawing), pace);
+    std::fs::write(path, svg).map_err(|e| e.to_string())
+}
+
+/// Renders `drawing` as an svg document laid out on `viewport`.
+pub fn to_svg(drawing: &DisplayList, viewport: &Viewport) -> String {
+    to_svg_with_background(drawing, viewport, true)
+}
+
+/// Renders `drawing` as an svg document, leaving the background see-through unless
+/// `background` is set.
+pub fn to_svg_with_background(
+    drawing: &DisplayList,
+    viewport: &Viewport,
+    background: bool,
+) -> String {
+    let mut svg = if background {
+        header(viewport, &drawing.background())
+    } else {
+        open_tag(viewport)
+    };
+    for command in drawing.painted() {
+        if let Some(element) = element(command, viewport, &drawing.background()) {
+            svg.push_str("    ");
+            svg.push_str(&element.write(""));
+            svg.push('\n');
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Writes `drawing` to an svg file with lines merged into as few elements as possible.
+pub fn save_optimised_svg<P: AsRef<std::path::Path>>(
+    drawing: &DisplayList,
+    canvas: &Canvas,
+    path: P,
+) -> Result<(), String> {
+    let svg = to_optimised_svg(drawing, &canvas.viewport(drawing));
+    std::fs::write(path, svg).map_err(|e| e.to_string())
+}
+
+/// Renders `drawing` like [`to_svg`] but smaller. Runs of lines in the same style become
+/// one `<polyline>`, or one `<path>` if they don't all join up, and lines that are empty or
+/// repeat one already drawn in the run are left out. Paint order is kept, so the image
+/// looks the same.
+pub fn to_optimised_svg(drawing: &DisplayList, viewport: &Viewport) -> String {
+    let mut svg = header(viewport, &drawing.background());
+    let mut run = LineRun::default();
+    for command in drawing.painted() {
+        // Reversed lines invert each other where they overlap, and dashed lines each start
+        // at their own offset, so both stay separate.
+        let separate = matches!(
+            command,
+            DrawCommand::Line { style, .. } if style.ink == Ink::Reverse || style.dash.is_some()
+        );
+        if let (DrawCommand::Line { start, end, style }, false) = (command, separate) {
+            let Some((start, end)) =
+                viewport.clip(viewport.to_image(start), viewport.to_image(end))
+            else {
+                continue;
+            };
+            let style = stroke(style, &drawing.background());
+            if run.style.as_ref() != Some(&style) {
+                run.write(&mut svg);
+                run.style = Some(style);
+            }
+            run.push(
+                (number(start.0), number(start.1)),
+                (number(end.0), number(end.1)),
+            );
+        } else if let Some(element) = element(command, viewport, &drawing.background()) {
+            run.write(&mut svg);
+            svg.push_str("    ");
+            svg.push_str(&element.write(""));
+            svg.push('\n');
+        }
+    }
+    run.write(&mut svg);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A point with its coordinates already formatted, so points that print the same are
+/// treated as the same.
+type Printed = (String, String);
+
+/// Consecutive lines drawn in the same style, as the polylines they join up into.
+#[derive(Default)]
+struct LineRun {
+    /// Stroke attributes.
+    style: Option<String>,
+    polylines: Vec<Vec<Printed>>,
+    seen: std::collections::HashSet<(Printed, Printed)>,
+}
+
+impl LineRun {
+    fn push(&mut self, start: Printed, end: Printed) {
+        if start == end {
+            return;
+        }
+        let key = if start <= end {
+            (start.clone(), end.clone())
+        } else {
+            (end.clone(), start.clone())
+        };
+        if !self.seen.insert(key) {
+            return;
+        }
+        match self.polylines.last_mut() {
+            Some(polyline) if polyline.last() == Some(&start) => polyline.push(end),
+            _ => self.polylines.push(vec![start, end]),
+        }
+    }
+
+    /// Writes the run out as a single element and empties it.
+    fn write(&mut self, svg: &mut String) {
+        let polylines = std::mem::take(&mut self.polylines);
+        self.seen.clear();
+        let Some(style) = &self.style else {
+            return;
+        };
+        // Bevelled joins are closest to how separate lines meet at a corner.
+        let stroke = format!("fill=\"none\" {style} stroke-linejoin=\"bevel\"");
+        match polylines.as_slice() {
+            [] => return,
+            [line] if line.len() == 2 => svg.push_str(&format!(
+                "    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {style}/>",
+                line[0].0, line[0].1, line[1].0, line[1].1
+            )),
+            [polyline] => {
+                let points: Vec<String> =
+                    polyline.iter().map(|(x, y)| format!("{x},{y}")).collect();
+                svg.push_str(&format!(
+                    "    <polyline points=\"{}\" {stroke}/>",
+                    points.join(" ")
+                ));
+            }
+            polylines => {
+                let data: Vec<String> = polylines
+                    .iter()
+                    .map(|polyline| {
+                        let points: Vec<String> =
+                            polyline.iter().map(|(x, y)| format!("{x} {y}")).collect();
+                        format!("M {}", points.join(" L "))
+                    })
+                    .collect();
+                svg.push_str(&format!("    <path d=\"{}\" {stroke}/>", data.join(" ")));
+            }
+        }
+        svg.push('\n');
+    }
+}
+
+/// Renders `drawing` as an svg document where each command appears in turn, with lines
+/// and arcs traced out by animating their dash offset and a turtle moving along the path.
+pub fn to_animated_svg(drawing: &DisplayList, viewport: &Viewport, pace: Pace) -> String {
+    let total_length: f64 = drawing.commands().iter().map(|c| c.length()).sum();
+    let seconds_per_unit = match pace {
+        Pace::Duration(seconds) if total_length > 0f64 => seconds / total_length,
+        Pace::Duration(_) => 0f64,
+        Pace::Speed(speed) => 1f64 / speed,
+    };
+
+    let mut svg = header(viewport, &drawing.background());
+    // Guides are there from the start rather than drawn by the turtle.
+    for guide in drawing.guides() {
+        if let Some(element) = element(guide, viewport, &drawing.background()) {
+            svg.push_str("    ");
+            svg.push_str(&element.write(""));
+            svg.push('\n');
+        }
+    }
+    let mut time = 0f64;
+    let mut motion = String::new();
+    let mut last_point = None;
+    for command in drawing.commands() {
+        let duration = command.length() * seconds_per_unit;
+        // The dash offset is what traces each stroke out, so dashed strokes draw solid.
+        let command = &solid(command);
+        if let Some(element) = element(command, viewport, &drawing.background()) {
+            // How long the stroke on the image is, or `None` if this isn't a stroke.
+            let (animation, stroked) = match command {
+                DrawCommand::Line { start, end, .. } => {
+                    // Only part of the line may be on the image, so only animate that part
+                    // over the time the turtle spends drawing it.
+                    let (start, end) = (viewport.to_image(start), viewport.to_image(end));
+                    let (from, to) = viewport.clip(start, end).unwrap_or((start, end));
+                    let hidden = distance(start, from) * seconds_per_unit;
+                    let shown = distance(from, to);
+                    (
+                        trace(shown, time + hidden, shown * seconds_per_unit),
+                        Some(shown),
+                    )
+                }
+                DrawCommand::Arc { .. } => (
+                    trace(command.length(), time, duration),
+                    Some(command.length()),
+                ),
+                DrawCommand::Fill { .. } | DrawCommand::Text { .. } => (
+                    format!(
+                        "<set attributeName=\"visibility\" to=\"visible\" begin=\"{}s\"/>",
+                        number(time)
+                    ),
+                    None,
+                ),
+            };
+            let element = match stroked {
+                Some(length) => {
+                    let length = number(length);
+                    element
+                        .with_attribute("stroke-dasharray", &length)
+                        .with_attribute("stroke-dashoffset", &length)
+                }
+                None => element.with_attribute("visibility", "hidden"),
+            };
+            svg.push_str("    ");
+            svg.push_str(&element.write(&animation));
+            svg.push('\n');
+        }
+        if let Some((from, to, data)) = motion_path(command, viewport) {
+            if last_point != Some(from) {
+                motion.push_str(&format!("M {} {} ", number(from.0), number(from.1)));
+            }
+            motion.push_str(&data);
+            last_point = Some(to);
+        }
+        time += duration;
+    }
+
+    if !motion.is_empty() {
+        svg.push_str(&format!(
+            "    <polygon points=\"8,0 -5,5 -5,-5\" fill=\"#ffffff\" stroke=\"#808080\"><animateMotion path=\"{}\" dur=\"{}s\" rotate=\"auto\" fill=\"freeze\"/></polygon>\n",
+            motion.trim_end(),
+            number(time.max(0.001))
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn header(viewport: &Viewport, background: &Color) -> String {
+    let (width, height) = (viewport.width, viewport.height);
+    format!(
+        "{}    <rect width=\"{width}\" height=\"{height}\" fill=\"{}\"/>\n",
+        open_tag(viewport),
+        colour_hex(background)
+    )
+}
+
+fn open_tag(viewport: &Viewport) -> String {
+    let (width, height) = (viewport.width, viewport.height);
+    format!(
+        "<svg width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\" xmlns=\"http://www.w3.org/2000/svg\">\n"
+    )
+}
+
+/// `command` without any dashes.
+fn solid(command: &DrawCommand) -> DrawCommand {
+    let mut command = command.clone();
+    if let DrawCommand::Line { style, .. } | DrawCommand::Arc { style, .. } = &mut command {
+        style.dash = None;
+    }
+    command
+}
+
+/// Animates a stroke of `length` being drawn from `begin` for `duration` seconds.
+fn trace(length: f64, begin: f64, duration: f64) -> String {
+    format!(
+        "<animate attributeName=\"stroke-dashoffset\" from=\"{}\" to=\"0\" begin=\"{}s\" dur=\"{}s\" fill=\"freeze\"/>",
+        number(length),
+        number(begin),
+        number(duration.max(0.001))
+    )
+}
+
+fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    (to.0 - from.0).hypot(to.1 - from.1)
+}
+
+/// A point on the image.
+type Point = (f64, f64);
+
+/// Where the turtle starts and ends a command on the image, and the path data that takes
+/// it from one to the other.
+fn motion_path(command: &DrawCommand, viewport: &Viewport) -> Option<(Point, Point, String)> {
+    match command {
+        DrawCommand::Line { start, end, .. } => {
+            let (from, to) = (viewport.to_image(start), viewport.to_image(end));
+            Some((from, to, format!("L {} {} ", number(to.0), number(to.1))))
+        }
+        DrawCommand::Arc {
+            centre,
+            radius,
+            start,
+            sweep,
+            ..
+        } => {
+            let (from, to) = arc_ends(centre, *radius, *start, *sweep, viewport);
+            let centre = viewport.to_image(centre);
+            Some((from, to, arc_data(centre, to, *radius, *sweep)))
+        }
+        DrawCommand::Fill { .. } | DrawCommand::Text { .. } => None,
+    }
+}
+
+/// Where an arc starts and ends on the image.
+fn arc_ends(
+    centre: &Location,
+    radius: f64,
+    start: f64,
+    sweep: f64,
+    viewport: &Viewport,
+) -> ((f64, f64), (f64, f64)) {
+    let point = |heading: f64| {
+        let heading = heading.to_radians();
+        viewport.to_image(&Location::new(
+            centre.x() + radius * heading.sin(),
+            centre.y() + radius * heading.cos(),
+        ))
+    };
+    (point(start), point(start + sweep))
+}
+
+/// Path data for an arc around `centre` ending at `to`. Whole circles are drawn as two
+/// halves because a single svg arc can't start and end at the same point.
+fn arc_data(centre: (f64, f64), to: (f64, f64), radius: f64, sweep: f64) -> String {
+    // Clockwise for the turtle is clockwise on screen too, which is svg's sweep flag.
+    let clockwise = u8::from(sweep > 0f64);
+    let r = number(radius);
+    if sweep.abs() >= 360f64 {
+        let across = (2f64 * centre.0 - to.0, 2f64 * centre.1 - to.1);
+        return format!(
+            "A {r} {r} 0 0 {clockwise} {} {} A {r} {r} 0 0 {clockwise} {} {} ",
+            number(across.0),
+            number(across.1),
+            number(to.0),
+            number(to.1)
+        );
+    }
+    format!(
+        "A {r} {r} 0 {} {clockwise} {} {} ",
+        u8::from(sweep.abs() > 180f64),
+        number(to.0),
+        number(to.1)
+    )
+}
+
+/// An svg element waiting to be written out, so animations can be added inside it.
+struct Element {
+    tag: &'static str,
+    attributes: String,
+    text: String,
+}
+
+impl Element {
+    fn new(tag: &'static str, attributes: String) -> Self {
+        Element {
+            tag,
+            attributes,
+            text: String::new(),
+        }
+    }
+
+    fn with_attribute(mut self, name: &str, value: &str) -> Self {
+        self.attributes.push_str(&format!(" {name}=\"{value}\""));
+        self
+    }
+
+    fn write(&self, children: &str) -> String {
+        if self.text.is_empty() && children.is_empty() {
+            format!("<{} {}/>", self.tag, self.attributes)
+        } else {
+            format!(
+                "<{} {}>{}{}</{}>",
+                self.tag, self.attributes, self.text, children, self.tag
+            )
+        }
+    }
+}
+
+/// Stroke attributes for `style`. Erasing paints the background colour back, and
+/// reversing draws white with a difference blend so it inverts what it crosses.
+fn stroke(style: &Style, background: &Color) -> String {
+    let width = number(style.width);
+    match style.ink {
+        Ink::Paint => format!(
+            "stroke=\"{}\" stroke-width=\"{width}\"",
+            colour_hex(&style.colour)
+        ),
+        Ink::Erase => format!(
+            "stroke=\"{}\" stroke-width=\"{width}\"",
+            colour_hex(background)
+        ),
+        Ink::Reverse => {
+            format!(
+                "stroke=\"#ffffff\" stroke-width=\"{width}\" style=\"mix-blend-mode:difference\""
+            )
+        }
+    }
+}
+
+/// Dash attributes for a stroke whose first `skipped` units were clipped off the image, or
+/// nothing for a solid stroke.
+fn dashes(dash: &Option<Dash>, skipped: f64) -> String {
+    let Some(dash) = dash else {
+        return String::new();
+    };
+    let pattern: Vec<String> = dash.pattern.iter().map(|length| number(*length)).collect();
+    let offset = dash.offset + skipped;
+    if offset == 0f64 {
+        format!(" stroke-dasharray=\"{}\"", pattern.join(" "))
+    } else {
+        format!(
+            " stroke-dasharray=\"{}\" stroke-dashoffset=\"{}\"",
+            pattern.join(" "),
+            number(offset)
+        )
+    }
+}
+
+/// The svg element for a single command, or `None` if none of it is on the image.
+fn element(command: &DrawCommand, viewport: &Viewport, background: &Color) -> Option<Element> {
+    match command {
+        DrawCommand::Line { start, end, style } => {
+            let image_start = viewport.to_image(start);
+            let (start, end) = viewport.clip(image_start, viewport.to_image(end))?;
+            Some(Element::new(
+                "line",
+                format!(
+                    "x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}{}",
+                    number(start.0),
+                    number(start.1),
+                    number(end.0),
+                    number(end.1),
+                    stroke(style, background),
+                    dashes(&style.dash, distance(image_start, start))
+                ),
+            ))
+        }
+        DrawCommand::Arc {
+            centre,
+            radius,
+            start,
+            sweep,
+            style,
+        } => {
+            let (from, to) = arc_ends(centre, *radius, *start, *sweep, viewport);
+            let data = arc_data(viewport.to_image(centre), to, *radius, *sweep);
+            Some(Element::new(
+                "path",
+                format!(
+                    "d=\"M {} {} {}\" fill=\"none\" {}{}",
+                    number(from.0),
+                    number(from.1),
+                    data.trim_end(),
+                    stroke(style, background),
+                    dashes(&style.dash, 0f64)
+                ),
+            ))
+        }
+        DrawCommand::Fill { points, colour } => {
+            let points: Vec<String> = points
+                .iter()
+                .map(|point| {
+                    let (x, y) = viewport.to_image(point);
+                    format!("{},{}", number(x), number(y))
+                })
+                .collect();
+            Some(Element::new(
+                "polygon",
+                format!(
+                    "points=\"{}\" fill=\"{}\" stroke=\"none\"",
+                    points.join(" "),
+                    colour_hex(colour)
+                ),
+            ))
+        }
+        DrawCommand::Text {
+            position,
+            text,
+            style,
+        } => {
+            let (x, y) = viewport.to_image(position);
+            let mut element = Element::new(
+                "text",
+                format!(
+                    "x=\"{}\" y=\"{}\" fill=\"{}\" font-family=\"sans-serif\" font-size=\"{}\"",
+                    number(x),
+                    number(y),
+                    colour_hex(&style.colour),
+                    number(12f64 * style.width)
+                ),
+            );
+            element.text = escape(text);
+            Some(element)
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::fixtures::{line, style};
+    use crate::display::{Ink, Style};
+    use unsvg::COLORS;
+
+    fn viewport() -> Viewport {
+        Canvas::new(100, 100).viewport(&DisplayList::new())
+    }
+
+    #[test]
+    fn line_element() {
+        let line = DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 10.0),
+            style: style(4),
+        };
+        assert_eq!(
+            element(&line, &viewport(), &COLORS[0]).map(|e| e.write("")),
+            Some(String::from(
+                "<line x1=\"50\" y1=\"50\" x2=\"50\" y2=\"40\" stroke=\"#ff0000\" stroke-width=\"1\"/>"
+            ))
+        );
+    }
+
+    #[test]
+    fn line_off_image_is_dropped() {
+        let line = DrawCommand::Line {
+            start: Location::new(100.0, 100.0),
+            end: Location::new(200.0, 100.0),
+            style: style(4),
+        };
+        assert_eq!(
+            element(&line, &viewport(), &COLORS[0]).map(|e| e.write("")),
+            None
+        );
+    }
+
+    #[test]
+    fn arc_element() {
+        let arc = DrawCommand::Arc {
+            centre: Location::origin(),
+            radius: 10.0,
+            start: 0.0,
+            sweep: 90.0,
+            style: style(4),
+        };
+        assert_eq!(
+            element(&arc, &viewport(), &COLORS[0]).map(|e| e.write("")),
+            Some(String::from(
+                "<path d=\"M 50 40 A 10 10 0 0 1 60 50\" fill=\"none\" stroke=\"#ff0000\" stroke-width=\"1\"/>"
+            ))
+        );
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let text = DrawCommand::Text {
+            position: Location::origin(),
+            text: String::from("a<b"),
+            style: style(4),
+        };
+        let element = element(&text, &viewport(), &COLORS[0]).unwrap().write("");
+        assert!(element.ends_with(">a&lt;b</text>"));
+    }
+
+    #[test]
+    fn document_has_background() {
+        let svg = to_svg(&DisplayList::new(), &viewport());
+        assert!(svg.contains("<rect width=\"100\" height=\"100\" fill=\"#000000\"/>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn whole_circle_is_two_halves() {
+        let circle = DrawCommand::Arc {
+            centre: Location::origin(),
+            radius: 10.0,
+            start: 0.0,
+            sweep: 360.0,
+            style: style(4),
+        };
+        let element = element(&circle, &viewport(), &COLORS[0]).unwrap().write("");
+        assert!(element.contains("d=\"M 50 40 A 10 10 0 0 1 50 60 A 10 10 0 0 1 50 40\""));
+    }
+
+    #[test]
+    fn animation_follows_drawing_order() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 10.0),
+            style: style(4),
+        });
+        drawing.push(DrawCommand::Line {
+            start: Location::new(0.0, 10.0),
+            end: Location::new(30.0, 10.0),
+            style: style(4),
+        });
+        let svg = to_animated_svg(&drawing, &viewport(), Pace::Duration(4.0));
+        assert!(svg.contains("from=\"10\" to=\"0\" begin=\"0s\" dur=\"1s\""));
+        assert!(svg.contains("from=\"30\" to=\"0\" begin=\"1s\" dur=\"3s\""));
+        assert!(svg.contains("path=\"M 50 50 L 50 40 L 80 40\" dur=\"4s\""));
+    }
+
+    #[test]
+    fn animation_speed() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 10.0),
+            style: style(4),
+        });
+        drawing.push(DrawCommand::Text {
+            position: Location::origin(),
+            text: String::from("done"),
+            style: style(4),
+        });
+        let svg = to_animated_svg(&drawing, &viewport(), Pace::Speed(5.0));
+        assert!(svg.contains("dur=\"2s\""));
+        assert!(svg.contains("visibility=\"hidden\""));
+        assert!(svg.contains("to=\"visible\" begin=\"2s\""));
+    }
+
+    #[test]
+    fn clipped_line_animates_visible_part() {
+        let mut drawing = DisplayList::new();
+        drawing.push(DrawCommand::Line {
+            start: Location::new(-100.0, 0.0),
+            end: Location::new(0.0, 0.0),
+            style: style(4),
+        });
+        let svg = to_animated_svg(&drawing, &viewport(), Pace::Speed(10.0));
+        assert!(svg.contains("stroke-dasharray=\"50\" stroke-dashoffset=\"50\">"));
+        assert!(svg.contains("from=\"50\" to=\"0\" begin=\"5s\" dur=\"5s\""));
+    }
+
+    fn body(svg: &str) -> Vec<&str> {
+        svg.lines().skip(2).map(str::trim).collect()
+    }
+
+    #[test]
+    fn optimised_joins_lines_into_polyline() {
+        let mut drawing = DisplayList::new();
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 4));
+        drawing.push(line((0.0, 10.0), (10.0, 10.0), 4));
+        drawing.push(line((10.0, 10.0), (10.0, 10.0), 4));
+        assert_eq!(
+            body(&to_optimised_svg(&drawing, &viewport())),
+            vec![
+                "<polyline points=\"50,50 50,40 60,40\" fill=\"none\" stroke=\"#ff0000\" stroke-width=\"1\" stroke-linejoin=\"bevel\"/>",
+                "</svg>"
+            ]
+        );
+    }
+
+    #[test]
+    fn optimised_splits_path_and_drops_repeats() {
+        let mut drawing = DisplayList::new();
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 4));
+        drawing.push(line((0.0, 10.0), (0.0, 0.0), 4));
+        drawing.push(line((20.0, 0.0), (30.0, 0.0), 4));
+        assert_eq!(
+            body(&to_optimised_svg(&drawing, &viewport())),
+            vec![
+                "<path d=\"M 50 50 L 50 40 M 70 50 L 80 50\" fill=\"none\" stroke=\"#ff0000\" stroke-width=\"1\" stroke-linejoin=\"bevel\"/>",
+                "</svg>"
+            ]
+        );
+    }
+
+    #[test]
+    fn optimised_keeps_paint_order() {
+        let mut drawing = DisplayList::new();
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 4));
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 1));
+        drawing.push(line((0.0, 0.0), (0.0, 10.0), 4));
+        let svg = to_optimised_svg(&drawing, &viewport());
+        // The red line drawn again over the blue one has to stay.
+        assert_eq!(body(&svg).len(), 4);
+        assert!(body(&svg)[2].contains("#ff0000"));
+    }
+
+    #[test]
+    fn optimised_is_smaller() {
+        let mut drawing = DisplayList::new();
+        for step in 0..20 {
+            let (from, to) = (step as f64, step as f64 + 1.0);
+            drawing.push(line((from, from), (to, to), 4));
+        }
+        let viewport = viewport();
+        assert!(
+            to_optimised_svg(&drawing, &viewport).len() * 4 < to_svg(&drawing, &viewport).len()
+        );
+    }
+
+    #[test]
+    fn background_colour() {
+        let mut drawing = DisplayList::new();
+        drawing.set_background(COLORS[6]);
+        let svg = to_svg(&drawing, &viewport());
+        assert!(svg.contains("<rect width=\"100\" height=\"100\" fill=\"#ffff00\"/>"));
+    }
+
+    #[test]
+    fn erase_and_reverse_strokes() {
+        let mut drawing = DisplayList::new();
+        drawing.set_background(COLORS[2]);
+        for ink in [Ink::Erase, Ink::Reverse] {
+            drawing.push(DrawCommand::Line {
+                start: Location::origin(),
+                end: Location::new(0.0, 10.0),
+                style: Style { ink, ..style(4) },
+            });
+        }
+        let svg = to_svg(&drawing, &viewport());
+        assert!(svg.contains("y2=\"40\" stroke=\"#00ffff\" stroke-width=\"1\"/>"));
+        assert!(svg.contains(
+            "stroke=\"#ffffff\" stroke-width=\"1\" style=\"mix-blend-mode:difference\"/>"
+        ));
+        // Optimising must not merge or drop reversed lines.
+        let mut reversed = DisplayList::new();
+        for _ in 0..2 {
+            reversed.push(DrawCommand::Line {
+                start: Location::origin(),
+                end: Location::new(0.0, 10.0),
+                style: Style {
+                    ink: Ink::Reverse,
+                    ..style(4)
+                },
+            });
+        }
+        let optimised = to_optimised_svg(&reversed, &viewport());
+        assert_eq!(optimised.matches("mix-blend-mode").count(), 2);
+    }
+
+    #[test]
+    fn dashed_strokes() {
+        let dashed = Style {
+            dash: Some(Dash {
+                pattern: vec![8.0, 4.0],
+                offset: 3.0,
+            }),
+            ..style(4)
+        };
+        let line = DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 10.0),
+            style: dashed.clone(),
+        };
+        assert!(element(&line, &viewport(), &COLORS[0])
+            .unwrap()
+            .write("")
+            .ends_with("stroke-dasharray=\"8 4\" stroke-dashoffset=\"3\"/>"));
+        // The part clipped off the image still counts towards the offset.
+        let clipped = DrawCommand::Line {
+            start: Location::new(0.0, -60.0),
+            end: Location::new(0.0, 0.0),
+            style: dashed,
+        };
+        assert!(element(&clipped, &viewport(), &COLORS[0])
+            .unwrap()
+            .write("")
+            .contains("stroke-dashoffset=\"13\""));
+    }
+
+    #[test]
+    fn dashed_lines_not_merged() {
+        let mut drawing = DisplayList::new();
+        for (offset, end) in [(0.0, 10.0), (10.0, 20.0)] {
+            drawing.push(DrawCommand::Line {
+                start: Location::new(0.0, end - 10.0),
+                end: Location::new(0.0, end),
+                style: Style {
+                    dash: Some(Dash {
+                        pattern: vec![4.0, 4.0],
+                        offset,
+                    }),
+                    ..style(4)
+                },
+            });
+        }
+        let svg = to_optimised_svg(&drawing, &viewport());
+        assert_eq!(svg.matches("<line").count(), 2);
+        assert!(!to_animated_svg(&drawing, &viewport(), Pace::Speed(10.0)).contains("8 4"));
+    }
+
+    #[test]
+    fn guides_are_not_animated() {
+        let mut drawing = DisplayList::new();
+        drawing.set_guides(vec![DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(0.0, 10.0),
+            style: style(4),
+        }]);
+        let svg = to_animated_svg(&drawing, &viewport(), Pace::Speed(10.0));
+        assert!(svg.contains("<line"));
+        assert!(!svg.contains("<animate"));
+        assert!(to_svg(&drawing, &viewport()).contains("<line"));
+    }
+}
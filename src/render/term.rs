@@ -0,0 +1,256 @@
+use crate::canvas::Viewport;
+use crate::display::DisplayList;
+use crate::render::plot::{strokes, Point};
+use unsvg::Color;
+
+/// Which characters the terminal preview draws with.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Glyphs {
+    /// Braille dots, 2 across and 4 down in each character.
+    Braille,
+    /// Box-drawing lines, one per character.
+    Box,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TermOptions {
+    pub glyphs: Glyphs,
+    /// Colour each character with its pen colour using ANSI escapes.
+    pub colour: bool,
+    /// How many characters wide the preview is.
+    pub columns: usize,
+}
+
+/// The terminal's width, asked of the tty on stdout. Falls back to `COLUMNS` when stdout
+/// isn't a terminal, and then to 80.
+pub fn terminal_columns() -> usize {
+    tty_columns()
+        .or_else(|| {
+            std::env::var("COLUMNS")
+                .ok()
+                .and_then(|columns| columns.trim().parse().ok())
+        })
+        .filter(|columns| *columns > 0)
+        .unwrap_or(80)
+}
+
+#[cfg(unix)]
+fn tty_columns() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes a `winsize` through the pointer, which outlives the call.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(not(unix))]
+fn tty_columns() -> Option<usize> {
+    None
+}
+
+/// Draws `drawing` as lines of text to preview it in a terminal. Text, guides and the
+/// insides of fills are left out.
+pub fn to_term(drawing: &DisplayList, viewport: &Viewport, options: &TermOptions) -> String {
+    // Dots per character, and how many image pixels each dot covers. Characters are about
+    // twice as tall as they are wide, so braille dots come out square.
+    let (across, down) = match options.glyphs {
+        Glyphs::Braille => (2, 4),
+        Glyphs::Box => (1, 2),
+    };
+    let (width, height) = (viewport.width.max(1) as f64, viewport.height.max(1) as f64);
+    let columns = options.columns.max(1);
+    let pixels_per_dot = width / (columns * across) as f64;
+    let rows = ((height / pixels_per_dot) / down as f64).ceil().max(1f64) as usize;
+
+    let mut grid = Grid::new(columns, rows, across, down);
+    for stroke in strokes(drawing, viewport) {
+        for pair in stroke.points.windows(2) {
+            let dot = |(x, y): Point| (x / pixels_per_dot, y / pixels_per_dot);
+            grid.segment(dot(pair[0]), dot(pair[1]), stroke.colour, options.glyphs);
+        }
+    }
+    grid.render(options)
+}
+
+/// Box-drawing directions a character can hold.
+const HORIZONTAL: u8 = 1;
+const VERTICAL: u8 = 2;
+const RISING: u8 = 4;
+const FALLING: u8 = 8;
+
+#[derive(Clone, Copy, Default)]
+struct Cell {
+    /// Braille dots or box-drawing directions.
+    bits: u8,
+    colour: Option<Color>,
+}
+
+struct Grid {
+    columns: usize,
+    rows: usize,
+    across: usize,
+    down: usize,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    fn new(columns: usize, rows: usize, across: usize, down: usize) -> Self {
+        Grid {
+            columns,
+            rows,
+            across,
+            down,
+            cells: vec![Cell::default(); columns * rows],
+        }
+    }
+
+    /// Marks every dot along a segment given in dots.
+    fn segment(&mut self, from: Point, to: Point, colour: Color, glyphs: Glyphs) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let direction = if dy.abs() * 2f64 <= dx.abs() {
+            HORIZONTAL
+        } else if dx.abs() * 2f64 <= dy.abs() {
+            VERTICAL
+        } else if (dx < 0f64) == (dy < 0f64) {
+            // y points down on the image, so this heads down to the right.
+            FALLING
+        } else {
+            RISING
+        };
+        let steps = dx.abs().max(dy.abs()).ceil().max(1f64) as usize;
+        for step in 0..=steps {
+            let fraction = step as f64 / steps as f64;
+            let (x, y) = (from.0 + dx * fraction, from.1 + dy * fraction);
+            let (x, y) = (x.floor(), y.floor());
+            if x < 0f64 || y < 0f64 {
+                continue;
+            }
+            let (x, y) = (x as usize, y as usize);
+            let (column, row) = (x / self.across, y / self.down);
+            if column >= self.columns || row >= self.rows {
+                continue;
+            }
+            let cell = &mut self.cells[row * self.columns + column];
+            cell.bits |= match glyphs {
+                Glyphs::Braille => braille_bit(x % self.across, y % self.down),
+                Glyphs::Box => direction,
+            };
+            cell.colour = Some(colour);
+        }
+    }
+
+    fn render(&self, options: &TermOptions) -> String {
+        let mut text = String::new();
+        for row in self.cells.chunks(self.columns) {
+            let mut current = None;
+            for cell in row {
+                if options.colour && cell.bits != 0 && cell.colour != current {
+                    if let Some(colour) = cell.colour {
+                        text.push_str(&format!(
+                            "\x1b[38;2;{};{};{}m",
+                            colour.red, colour.green, colour.blue
+                        ));
+                    }
+                    current = cell.colour;
+                }
+                text.push(match options.glyphs {
+                    Glyphs::Braille => char::from_u32(0x2800 + cell.bits as u32).unwrap_or(' '),
+                    Glyphs::Box => box_char(cell.bits),
+                });
+            }
+            if current.is_some() {
+                text.push_str("\x1b[0m");
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// The bit for the dot at `x`, `y` in a braille character.
+fn braille_bit(x: usize, y: usize) -> u8 {
+    const BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+    BITS[x][y]
+}
+
+fn box_char(bits: u8) -> char {
+    match bits {
+        0 => ' ',
+        HORIZONTAL => '─',
+        VERTICAL => '│',
+        RISING => '╱',
+        FALLING => '╲',
+        bits if bits == RISING | FALLING => '╳',
+        _ => '┼',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Canvas;
+    use crate::display::fixtures::line;
+    use unsvg::COLORS;
+
+    fn options(glyphs: Glyphs) -> TermOptions {
+        TermOptions {
+            glyphs,
+            colour: false,
+            columns: 4,
+        }
+    }
+
+    #[test]
+    fn sized_to_columns() {
+        let viewport = Canvas::new(80, 80).viewport(&DisplayList::new());
+        let text = to_term(&DisplayList::new(), &viewport, &options(Glyphs::Braille));
+        // 4 columns of 2 dots over 80 pixels is 10 pixels a dot, and 8 dots is 2 rows.
+        assert_eq!(text, "\u{2800}\u{2800}\u{2800}\u{2800}\n".repeat(2));
+    }
+
+    #[test]
+    fn box_lines() {
+        let viewport = Canvas::new(40, 40).viewport(&DisplayList::new());
+        let mut drawing = DisplayList::new();
+        drawing.push(line((-20.0, 0.0), (20.0, 0.0), 1));
+        drawing.push(line((0.0, 20.0), (0.0, -20.0), 1));
+        let text = to_term(&drawing, &viewport, &options(Glyphs::Box));
+        assert_eq!(text, "  │ \n──┼─\n");
+    }
+
+    #[test]
+    fn braille_dots() {
+        let viewport = Canvas::new(8, 8).viewport(&DisplayList::new());
+        let mut drawing = DisplayList::new();
+        drawing.push(line((-4.0, 3.5), (-3.0, 3.5), 1));
+        let text = to_term(&drawing, &viewport, &options(Glyphs::Braille));
+        assert!(text.starts_with('\u{2809}'));
+    }
+
+    #[test]
+    fn ansi_colours() {
+        let viewport = Canvas::new(40, 40).viewport(&DisplayList::new());
+        let mut drawing = DisplayList::new();
+        drawing.push(line((-20.0, 0.0), (20.0, 0.0), 1));
+        let options = TermOptions {
+            colour: true,
+            ..options(Glyphs::Box)
+        };
+        let text = to_term(&drawing, &viewport, &options);
+        let blue = COLORS[1];
+        assert!(text.contains(&format!(
+            "\x1b[38;2;{};{};{}m────\x1b[0m",
+            blue.red, blue.green, blue.blue
+        )));
+    }
+
+    #[test]
+    fn columns_are_never_zero() {
+        // Whether or not the tests run in a terminal, some width comes back.
+        assert!(terminal_columns() > 0);
+    }
+}
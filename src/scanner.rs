@@ -2,24 +2,23 @@ use crate::error::Error;
 use crate::token::Token;
 use crate::token::TokenType;
 use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
 pub struct Scanner {
     source: String,
     tokens: Vec<Token>,
     line: u32,
     column: u32,
-    start: u32,
-    current: u32,
 }
 
 impl Scanner {
-    fn new(source: String) -> Self {
+    pub fn new(source: String) -> Self {
         Scanner {
             source,
             tokens: Vec::new(),
             line: 1u32,
             column: 1u32,
-            start: 0u32,
-            current: 0u32,
         }
     }
 
@@ -30,112 +29,99 @@ impl Scanner {
             _ => false,
         }
     }
+
+    /// Whether `c` ends a word, number or variable name.
+    fn is_delimiter(c: char) -> bool {
+        c.is_whitespace() || c == '[' || c == ']'
+    }
 }
 
 impl Scanner {
-    fn scan(&mut self) {
+    /// Splits the source into tokens, stopping at the first character that can't start one.
+    pub fn scan(&mut self) -> Result<(), Error> {
         let binding = self.source.clone();
         let mut chars = binding.chars().peekable();
         while let Some(character) = chars.next() {
-            self.start = self.current;
+            let (line, column) = (self.line, self.column);
+            self.column += 1;
             match character {
                 //One character lexemes
-                '+' => self.add_token(TokenType::Plus, self.substring()),
-                '-' => self.add_token(TokenType::Minus, self.substring()),
-                '*' => self.add_token(TokenType::Star, self.substring()),
+                '+' => self.add_token(TokenType::Plus, String::from("+"), line, column),
+                '-' => self.add_token(TokenType::Minus, String::from("-"), line, column),
+                '*' => self.add_token(TokenType::Star, String::from("*"), line, column),
+                '[' => self.add_token(TokenType::LeftBracket, String::from("["), line, column),
+                ']' => self.add_token(TokenType::RightBracket, String::from("]"), line, column),
                 //Longer Lexemes
                 '/' => {
-                    if *chars.peek().unwrap() == '/' {
-                        chars.next();
-                        self.current += 1;
+                    if chars.peek() == Some(&'/') {
                         while let Some(character) = chars.peek() {
                             if *character == '\n' {
                                 break;
                             }
                             chars.next();
-                            self.current += 1;
                             self.column += 1;
                         }
                     } else {
-                        self.add_token(TokenType::Slash, self.substring())
+                        self.add_token(TokenType::Slash, String::from("/"), line, column)
                     }
                 }
                 //Whitespace and New line
-                ' ' => {}
                 '\n' => {
                     self.line += 1;
-                    self.column = 0;
+                    self.column = 1;
                 }
+                other if other.is_whitespace() => {}
                 '\"' => {
-                    while let Some(character) = chars.peek() {
-                        if (character == &' ') | (character == &'\n') {
-                            break;
-                        }
-                        chars.next();
-                        self.current += 1;
-                        self.column += 1;
-                    }
-                    self.add_token(TokenType::Value, self.substring())
+                    let lexeme = self.substring(character, &mut chars);
+                    self.add_token(TokenType::Value, lexeme, line, column)
                 }
                 ':' => {
-                    while let Some(character) = chars.peek() {
-                        if (character == &' ') | (character == &'\n') {
-                            break;
-                        }
-                        chars.next();
-                        self.current += 1;
-                        self.column += 1;
-                    }
-                    self.add_token(TokenType::Identifier, self.substring())
+                    let lexeme = self.substring(character, &mut chars);
+                    self.add_token(TokenType::Identifier, lexeme, line, column)
+                }
+                other if other.is_ascii_digit() || other == '.' => {
+                    let lexeme = self.substring(character, &mut chars);
+                    self.add_token(TokenType::Number, lexeme, line, column)
+                }
+                other if Scanner::is_alphabet(other) => {
+                    let lexeme = self.substring(character, &mut chars);
+                    self.keyword_processing(lexeme, line, column);
                 }
                 other => {
-                    if Scanner::is_alphabet(other) {
-                        while let Some(character) = chars.peek() {
-                            if (character == &' ') | (character == &'\n') {
-                                break;
-                            }
-                            chars.next();
-                            self.current += 1;
-                            self.column += 1;
-                        }
-
-                        let lexeme = self.substring();
-                        println!("{}", lexeme);
-                        match self.keyword_processing(lexeme) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                println!("implement error reporting manager. btw there is error at line {} and column {}, it says {}", e.get_line(), e.get_column(), e.get_message())
-                            }
-                        }
-                    } else {
-                        println!(
-                            "change to error system, error at line {} in column {}",
-                            self.line, self.column
-                        );
-                    }
+                    return Err(Error::new(
+                        format!("Unexpected character '{other}'"),
+                        (line, column),
+                    ))
                 }
             }
-            self.current += 1;
-            self.column += 1;
-        }
-        for token in &self.tokens {
-            println!("token: {}", token.to_string());
         }
+        Ok(())
     }
-    fn substring(&self) -> String {
-        println!(
-            "start: {}, current: {}, len: {}",
-            self.start,
-            self.current,
-            self.source.len()
-        );
-        String::from(&self.source[(self.start as usize)..=(self.current as usize)])
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
     }
 
-    fn process_character(&mut self, char_iterator: Vec<u32>) {
-        todo!();
+    pub fn into_tokens(self) -> Vec<Token> {
+        self.tokens
     }
-    fn keyword_processing(&mut self, lexeme: String) -> Result<(), Error> {
+
+    /// Reads the rest of the lexeme starting with `first`, up to the next delimiter.
+    fn substring(&mut self, first: char, chars: &mut Peekable<Chars>) -> String {
+        let mut lexeme = String::from(first);
+        while let Some(&character) = chars.peek() {
+            if Scanner::is_delimiter(character) {
+                break;
+            }
+            lexeme.push(character);
+            chars.next();
+            self.column += 1;
+        }
+        lexeme
+    }
+
+    /// Adds a keyword, in any case, or else a word naming a procedure.
+    fn keyword_processing(&mut self, lexeme: String, line: u32, column: u32) {
         let mut map = HashMap::new();
         map.insert("PenUp", TokenType::PenUp);
         map.insert("PenDown", TokenType::PenDown);
@@ -144,6 +130,7 @@ impl Scanner {
         map.insert("Left", TokenType::Left);
         map.insert("Right", TokenType::Right);
         map.insert("SetPenColour", TokenType::SetPenColour);
+        map.insert("SetPenColor", TokenType::SetPenColour);
         map.insert("Turn", TokenType::Turn);
         map.insert("SetHeading", TokenType::SetHeading);
         map.insert("SetX", TokenType::SetX);
@@ -154,6 +141,7 @@ impl Scanner {
         map.insert("YCor", TokenType::YCor);
         map.insert("Heading", TokenType::Heading);
         map.insert("Colour", TokenType::Colour);
+        map.insert("Color", TokenType::Colour);
         map.insert("If", TokenType::If);
         map.insert("While", TokenType::While);
         map.insert("EQ", TokenType::Eq);
@@ -162,20 +150,48 @@ impl Scanner {
         map.insert("LT", TokenType::Lt);
         map.insert("AND", TokenType::And);
         map.insert("OR", TokenType::Or);
-        match map.get(&lexeme[..]) {
-            Some(x) => {
-                self.add_token(x.clone(), lexeme);
-                Ok(())
-            }
-            None => Err(Error::new(
-                "keyword doesn't exist".to_string(),
-                (self.line, self.column),
-            )),
-        }
+        map.insert("Wrap", TokenType::Wrap);
+        map.insert("Fence", TokenType::Fence);
+        map.insert("Window", TokenType::Window);
+        map.insert("SetBackground", TokenType::SetBackground);
+        map.insert("ShowTurtle", TokenType::ShowTurtle);
+        map.insert("HideTurtle", TokenType::HideTurtle);
+        map.insert("NewTurtle", TokenType::NewTurtle);
+        map.insert("Tell", TokenType::Tell);
+        map.insert("Ask", TokenType::Ask);
+        map.insert("PushState", TokenType::PushState);
+        map.insert("PopState", TokenType::PopState);
+        map.insert("LSystem", TokenType::LSystem);
+        map.insert("SetScale", TokenType::SetScale);
+        map.insert("SetOrigin", TokenType::SetOrigin);
+        map.insert("SetRotation", TokenType::SetRotation);
+        map.insert("PushTransform", TokenType::PushTransform);
+        map.insert("PopTransform", TokenType::PopTransform);
+        map.insert("SetPos", TokenType::SetPos);
+        map.insert("Home", TokenType::Home);
+        map.insert("Pos", TokenType::Pos);
+        map.insert("Towards", TokenType::Towards);
+        map.insert("Distance", TokenType::Distance);
+        map.insert("PenErase", TokenType::PenErase);
+        map.insert("PenReverse", TokenType::PenReverse);
+        map.insert("PenMode", TokenType::PenMode);
+        map.insert("SetPenStyle", TokenType::SetPenStyle);
+        map.insert("Arc", TokenType::Arc);
+        map.insert("Label", TokenType::Label);
+        map.insert("BeginFill", TokenType::BeginFill);
+        map.insert("EndFill", TokenType::EndFill);
+        map.insert("SetPenSize", TokenType::SetPenSize);
+        map.insert("To", TokenType::To);
+        map.insert("End", TokenType::End);
+        let token_type = map
+            .iter()
+            .find(|(keyword, _)| keyword.eq_ignore_ascii_case(&lexeme))
+            .map_or(TokenType::Word, |(_, token_type)| *token_type);
+        self.add_token(token_type, lexeme, line, column);
     }
 
-    fn add_token(&mut self, token_type: TokenType, lexeme: String) {
-        let token = Token::new(lexeme, token_type, self.line, self.column);
+    fn add_token(&mut self, token_type: TokenType, lexeme: String, line: u32, column: u32) {
+        let token = Token::new(lexeme, token_type, line, column);
         self.tokens.push(token);
     }
 }
@@ -183,20 +199,107 @@ impl Scanner {
 #[cfg(test)]
 pub mod test {
     use super::Scanner;
+    use crate::token::TokenType;
+
+    fn token_types(input: &str) -> Vec<TokenType> {
+        let mut scanner = Scanner::new(String::from(input));
+        assert!(scanner.scan().is_ok());
+        scanner
+            .tokens()
+            .iter()
+            .map(|token| token.token_type())
+            .collect()
+    }
+
     #[test]
     fn test1() {
         let input = String::from("PenDown\nForward \"100 ");
         let mut scanner = Scanner::new(input.clone());
-        scanner.scan();
-        //todo!();
+        assert!(scanner.scan().is_ok());
+        let tokens = scanner.tokens();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[2].lexeme(), "\"100");
+        assert_eq!((tokens[2].line(), tokens[2].column()), (2, 9));
     }
 
     #[test]
     fn ignores_comments() {
         let input = String::from("PenDown\n//hello world\nForward \"100");
         let mut scanner = Scanner::new(input.clone());
-        scanner.scan();
-        //println!("{}",scanner.tokens);
-        todo!()
+        assert!(scanner.scan().is_ok());
+        let types: Vec<TokenType> = scanner.tokens().iter().map(|t| t.token_type()).collect();
+        assert_eq!(
+            types,
+            vec![TokenType::PenDown, TokenType::Forward, TokenType::Value]
+        );
+        assert_eq!(scanner.tokens()[1].line(), 3);
+    }
+
+    #[test]
+    fn comment_at_end_of_file() {
+        assert_eq!(token_types("PENUP //"), vec![TokenType::PenUp]);
+        assert_eq!(
+            token_types("PENUP /"),
+            vec![TokenType::PenUp, TokenType::Slash]
+        );
+    }
+
+    #[test]
+    fn keywords_in_any_case() {
+        assert_eq!(
+            token_types("PENDOWN setpencolor \"1 COLOR"),
+            vec![
+                TokenType::PenDown,
+                TokenType::SetPenColour,
+                TokenType::Value,
+                TokenType::Colour
+            ]
+        );
+    }
+
+    #[test]
+    fn brackets_split_words() {
+        assert_eq!(
+            token_types("IF :x [FORWARD \"10]"),
+            vec![
+                TokenType::If,
+                TokenType::Identifier,
+                TokenType::LeftBracket,
+                TokenType::Forward,
+                TokenType::Value,
+                TokenType::RightBracket
+            ]
+        );
+        assert_eq!(
+            token_types("SETPOS [3 4.5]"),
+            vec![
+                TokenType::SetPos,
+                TokenType::LeftBracket,
+                TokenType::Number,
+                TokenType::Number,
+                TokenType::RightBracket
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_words_name_procedures() {
+        assert_eq!(
+            token_types("TO Box \"Arg END Box"),
+            vec![
+                TokenType::To,
+                TokenType::Word,
+                TokenType::Value,
+                TokenType::End,
+                TokenType::Word
+            ]
+        );
+    }
+
+    #[test]
+    fn unexpected_character() {
+        let mut scanner = Scanner::new(String::from("FORWARD\n  \"1 ?"));
+        let error = scanner.scan().err().unwrap();
+        assert_eq!(error.get_location(), (2, 6));
     }
 }
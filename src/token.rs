@@ -1,15 +1,19 @@
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TokenType {
     //single character tokens
     Plus,
     Minus,
     Star,
     Slash,
+    LeftBracket,
+    RightBracket,
     // few character tokens
 
     // literals
     Value,
     Identifier,
+    Number,
+    Word,
 
     //keywords
     PenUp,
@@ -37,6 +41,39 @@ pub enum TokenType {
     Lt,
     And,
     Or,
+    Wrap,
+    Fence,
+    Window,
+    SetBackground,
+    ShowTurtle,
+    HideTurtle,
+    NewTurtle,
+    Tell,
+    Ask,
+    PushState,
+    PopState,
+    LSystem,
+    SetScale,
+    SetOrigin,
+    SetRotation,
+    PushTransform,
+    PopTransform,
+    SetPos,
+    Home,
+    Pos,
+    Towards,
+    Distance,
+    PenErase,
+    PenReverse,
+    PenMode,
+    SetPenStyle,
+    Arc,
+    Label,
+    BeginFill,
+    EndFill,
+    SetPenSize,
+    To,
+    End,
 }
 impl TokenType {
     pub fn to_string(&self) -> String {
@@ -45,8 +82,12 @@ impl TokenType {
             TokenType::Minus => String::from("Minus"),
             TokenType::Star => String::from("Star"),
             TokenType::Slash => String::from("Slash"),
+            TokenType::LeftBracket => String::from("Left Bracket"),
+            TokenType::RightBracket => String::from("Right Bracket"),
             TokenType::Value => String::from("Value"),
             TokenType::Identifier => String::from("Identifier"),
+            TokenType::Number => String::from("Number"),
+            TokenType::Word => String::from("Word"),
             TokenType::PenUp => String::from("PenUp"),
             TokenType::PenDown => String::from("PenDown"),
             TokenType::Forward => String::from("Forward"),
@@ -72,6 +113,39 @@ impl TokenType {
             TokenType::Lt => String::from("Less Than"),
             TokenType::And => String::from("AND"),
             TokenType::Or => String::from("OR"),
+            TokenType::Wrap => String::from("Wrap"),
+            TokenType::Fence => String::from("Fence"),
+            TokenType::Window => String::from("Window"),
+            TokenType::SetBackground => String::from("SetBackground"),
+            TokenType::ShowTurtle => String::from("ShowTurtle"),
+            TokenType::HideTurtle => String::from("HideTurtle"),
+            TokenType::NewTurtle => String::from("NewTurtle"),
+            TokenType::Tell => String::from("Tell"),
+            TokenType::Ask => String::from("Ask"),
+            TokenType::PushState => String::from("PushState"),
+            TokenType::PopState => String::from("PopState"),
+            TokenType::LSystem => String::from("LSystem"),
+            TokenType::SetScale => String::from("SetScale"),
+            TokenType::SetOrigin => String::from("SetOrigin"),
+            TokenType::SetRotation => String::from("SetRotation"),
+            TokenType::PushTransform => String::from("PushTransform"),
+            TokenType::PopTransform => String::from("PopTransform"),
+            TokenType::SetPos => String::from("SetPos"),
+            TokenType::Home => String::from("Home"),
+            TokenType::Pos => String::from("Pos"),
+            TokenType::Towards => String::from("Towards"),
+            TokenType::Distance => String::from("Distance"),
+            TokenType::PenErase => String::from("PenErase"),
+            TokenType::PenReverse => String::from("PenReverse"),
+            TokenType::PenMode => String::from("PenMode"),
+            TokenType::SetPenStyle => String::from("SetPenStyle"),
+            TokenType::Arc => String::from("Arc"),
+            TokenType::Label => String::from("Label"),
+            TokenType::BeginFill => String::from("BeginFill"),
+            TokenType::EndFill => String::from("EndFill"),
+            TokenType::SetPenSize => String::from("SetPenSize"),
+            TokenType::To => String::from("To"),
+            TokenType::End => String::from("End"),
         }
     }
 }
@@ -93,6 +167,24 @@ impl Token {
     }
 }
 
+impl Token {
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn token_type(&self) -> TokenType {
+        self.token_type
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
 impl Token {
     pub fn to_string(&self) -> String {
         format!(
@@ -193,6 +285,228 @@ mod test {
         let ttype = TokenType::Plus;
         assert_eq!(ttype.to_string(), "Plus");
     }
+    #[test]
+    fn token_type_wrap_tostring() {
+        let ttype = TokenType::Wrap;
+        assert_eq!(ttype.to_string(), "Wrap");
+    }
+
+    #[test]
+    fn token_type_fence_tostring() {
+        let ttype = TokenType::Fence;
+        assert_eq!(ttype.to_string(), "Fence");
+    }
+
+    #[test]
+    fn token_type_window_tostring() {
+        let ttype = TokenType::Window;
+        assert_eq!(ttype.to_string(), "Window");
+    }
+
+    #[test]
+    fn token_type_setbackground_tostring() {
+        let ttype = TokenType::SetBackground;
+        assert_eq!(ttype.to_string(), "SetBackground");
+    }
+
+    #[test]
+    fn token_type_showturtle_tostring() {
+        let ttype = TokenType::ShowTurtle;
+        assert_eq!(ttype.to_string(), "ShowTurtle");
+    }
+
+    #[test]
+    fn token_type_hideturtle_tostring() {
+        let ttype = TokenType::HideTurtle;
+        assert_eq!(ttype.to_string(), "HideTurtle");
+    }
+
+    #[test]
+    fn token_type_newturtle_tostring() {
+        let ttype = TokenType::NewTurtle;
+        assert_eq!(ttype.to_string(), "NewTurtle");
+    }
+
+    #[test]
+    fn token_type_tell_tostring() {
+        let ttype = TokenType::Tell;
+        assert_eq!(ttype.to_string(), "Tell");
+    }
+
+    #[test]
+    fn token_type_ask_tostring() {
+        let ttype = TokenType::Ask;
+        assert_eq!(ttype.to_string(), "Ask");
+    }
+
+    #[test]
+    fn token_type_pushstate_tostring() {
+        let ttype = TokenType::PushState;
+        assert_eq!(ttype.to_string(), "PushState");
+    }
+
+    #[test]
+    fn token_type_popstate_tostring() {
+        let ttype = TokenType::PopState;
+        assert_eq!(ttype.to_string(), "PopState");
+    }
+
+    #[test]
+    fn token_type_lsystem_tostring() {
+        let ttype = TokenType::LSystem;
+        assert_eq!(ttype.to_string(), "LSystem");
+    }
+
+    #[test]
+    fn token_type_setscale_tostring() {
+        let ttype = TokenType::SetScale;
+        assert_eq!(ttype.to_string(), "SetScale");
+    }
+
+    #[test]
+    fn token_type_setorigin_tostring() {
+        let ttype = TokenType::SetOrigin;
+        assert_eq!(ttype.to_string(), "SetOrigin");
+    }
+
+    #[test]
+    fn token_type_setrotation_tostring() {
+        let ttype = TokenType::SetRotation;
+        assert_eq!(ttype.to_string(), "SetRotation");
+    }
+
+    #[test]
+    fn token_type_pushtransform_tostring() {
+        let ttype = TokenType::PushTransform;
+        assert_eq!(ttype.to_string(), "PushTransform");
+    }
+
+    #[test]
+    fn token_type_poptransform_tostring() {
+        let ttype = TokenType::PopTransform;
+        assert_eq!(ttype.to_string(), "PopTransform");
+    }
+
+    #[test]
+    fn token_type_setpos_tostring() {
+        let ttype = TokenType::SetPos;
+        assert_eq!(ttype.to_string(), "SetPos");
+    }
+
+    #[test]
+    fn token_type_home_tostring() {
+        let ttype = TokenType::Home;
+        assert_eq!(ttype.to_string(), "Home");
+    }
+
+    #[test]
+    fn token_type_pos_tostring() {
+        let ttype = TokenType::Pos;
+        assert_eq!(ttype.to_string(), "Pos");
+    }
+
+    #[test]
+    fn token_type_towards_tostring() {
+        let ttype = TokenType::Towards;
+        assert_eq!(ttype.to_string(), "Towards");
+    }
+
+    #[test]
+    fn token_type_distance_tostring() {
+        let ttype = TokenType::Distance;
+        assert_eq!(ttype.to_string(), "Distance");
+    }
+
+    #[test]
+    fn token_type_penerase_tostring() {
+        let ttype = TokenType::PenErase;
+        assert_eq!(ttype.to_string(), "PenErase");
+    }
+
+    #[test]
+    fn token_type_penreverse_tostring() {
+        let ttype = TokenType::PenReverse;
+        assert_eq!(ttype.to_string(), "PenReverse");
+    }
+
+    #[test]
+    fn token_type_penmode_tostring() {
+        let ttype = TokenType::PenMode;
+        assert_eq!(ttype.to_string(), "PenMode");
+    }
+
+    #[test]
+    fn token_type_setpenstyle_tostring() {
+        let ttype = TokenType::SetPenStyle;
+        assert_eq!(ttype.to_string(), "SetPenStyle");
+    }
+
+    #[test]
+    fn token_type_leftbracket_tostring() {
+        let ttype = TokenType::LeftBracket;
+        assert_eq!(ttype.to_string(), "Left Bracket");
+    }
+
+    #[test]
+    fn token_type_rightbracket_tostring() {
+        let ttype = TokenType::RightBracket;
+        assert_eq!(ttype.to_string(), "Right Bracket");
+    }
+
+    #[test]
+    fn token_type_number_tostring() {
+        let ttype = TokenType::Number;
+        assert_eq!(ttype.to_string(), "Number");
+    }
+
+    #[test]
+    fn token_type_word_tostring() {
+        let ttype = TokenType::Word;
+        assert_eq!(ttype.to_string(), "Word");
+    }
+
+    #[test]
+    fn token_type_arc_tostring() {
+        let ttype = TokenType::Arc;
+        assert_eq!(ttype.to_string(), "Arc");
+    }
+
+    #[test]
+    fn token_type_label_tostring() {
+        let ttype = TokenType::Label;
+        assert_eq!(ttype.to_string(), "Label");
+    }
+
+    #[test]
+    fn token_type_beginfill_tostring() {
+        let ttype = TokenType::BeginFill;
+        assert_eq!(ttype.to_string(), "BeginFill");
+    }
+
+    #[test]
+    fn token_type_endfill_tostring() {
+        let ttype = TokenType::EndFill;
+        assert_eq!(ttype.to_string(), "EndFill");
+    }
+
+    #[test]
+    fn token_type_setpensize_tostring() {
+        let ttype = TokenType::SetPenSize;
+        assert_eq!(ttype.to_string(), "SetPenSize");
+    }
+
+    #[test]
+    fn token_type_to_tostring() {
+        let ttype = TokenType::To;
+        assert_eq!(ttype.to_string(), "To");
+    }
+
+    #[test]
+    fn token_type_end_tostring() {
+        let ttype = TokenType::End;
+        assert_eq!(ttype.to_string(), "End");
+    }
+
     //TODO need to add the remaining tests for new token types
     #[test]
     fn token_tostring() {
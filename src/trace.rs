@@ -0,0 +1,174 @@
+use crate::location::Location;
+use crate::render::number;
+use crate::turtle::Mode;
+
+/// What kind of command a trace event came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    /// A straight move, drawn or not. Wrapping splits a move into several events.
+    Move,
+    Arc,
+    Fill,
+    Label,
+}
+
+impl EventKind {
+    fn name(&self) -> &'static str {
+        match self {
+            EventKind::Move => "move",
+            EventKind::Arc => "arc",
+            EventKind::Fill => "fill",
+            EventKind::Label => "label",
+        }
+    }
+}
+
+/// One thing the turtle did, with its state at the time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub kind: EventKind,
+    pub start: Location,
+    pub end: Location,
+    /// The pen colour index.
+    pub colour: f32,
+    pub mode: Mode,
+    /// The heading in the turtle's angle convention.
+    pub heading: f64,
+    /// The source line of the command that caused it, if known.
+    pub line: Option<usize>,
+}
+
+/// Every event a program caused, in order, for checking geometry without comparing pixels.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Trace { events: Vec::new() }
+    }
+
+    pub fn push(&mut self, event: TraceEvent) {
+        self.events.push(event);
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// The trace as a JSON array of objects.
+    pub fn to_json(&self) -> String {
+        let events: Vec<String> = self
+            .events
+            .iter()
+            .map(|event| {
+                format!(
+                    "  {{\"kind\": \"{}\", \"start\": [{}, {}], \"end\": [{}, {}], \"colour\": {}, \"pen\": \"{}\", \"heading\": {}, \"line\": {}}}",
+                    event.kind.name(),
+                    number(event.start.x()),
+                    number(event.start.y()),
+                    number(event.end.x()),
+                    number(event.end.y()),
+                    number(event.colour as f64),
+                    pen(&event.mode),
+                    number(event.heading),
+                    event.line.map_or(String::from("null"), |line| line.to_string()),
+                )
+            })
+            .collect();
+        if events.is_empty() {
+            String::from("[]\n")
+        } else {
+            format!("[\n{}\n]\n", events.join(",\n"))
+        }
+    }
+
+    /// The trace as CSV with a header row. An unknown line is left empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("kind,start_x,start_y,end_x,end_y,colour,pen,heading,line\n");
+        for event in &self.events {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                event.kind.name(),
+                number(event.start.x()),
+                number(event.start.y()),
+                number(event.end.x()),
+                number(event.end.y()),
+                number(event.colour as f64),
+                pen(&event.mode),
+                number(event.heading),
+                event.line.map_or(String::new(), |line| line.to_string()),
+            ));
+        }
+        csv
+    }
+}
+
+fn pen(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::PenUp => "up",
+        Mode::PenDown => "down",
+        Mode::PenErase => "erase",
+        Mode::PenReverse => "reverse",
+    }
+}
+
+/// Writes `trace` as JSON.
+pub fn save_json<P: AsRef<std::path::Path>>(trace: &Trace, path: P) -> Result<(), String> {
+    std::fs::write(path, trace.to_json()).map_err(|e| e.to_string())
+}
+
+/// Writes `trace` as CSV.
+pub fn save_csv<P: AsRef<std::path::Path>>(trace: &Trace, path: P) -> Result<(), String> {
+    std::fs::write(path, trace.to_csv()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace() -> Trace {
+        let mut trace = Trace::new();
+        trace.push(TraceEvent {
+            kind: EventKind::Move,
+            start: Location::origin(),
+            end: Location::new(0.0, 10.5),
+            colour: 7.0,
+            mode: Mode::PenDown,
+            heading: 0.0,
+            line: Some(3),
+        });
+        trace.push(TraceEvent {
+            kind: EventKind::Arc,
+            start: Location::new(0.0, 20.5),
+            end: Location::new(10.0, 10.5),
+            colour: 1.0,
+            mode: Mode::PenUp,
+            heading: 90.0,
+            line: None,
+        });
+        trace
+    }
+
+    #[test]
+    fn json_trace() {
+        assert_eq!(
+            trace().to_json(),
+            "[\n  {\"kind\": \"move\", \"start\": [0, 0], \"end\": [0, 10.5], \"colour\": 7, \"pen\": \"down\", \"heading\": 0, \"line\": 3},\n  {\"kind\": \"arc\", \"start\": [0, 20.5], \"end\": [10, 10.5], \"colour\": 1, \"pen\": \"up\", \"heading\": 90, \"line\": null}\n]\n"
+        );
+    }
+
+    #[test]
+    fn empty_json_trace() {
+        assert_eq!(Trace::new().to_json(), "[]\n");
+    }
+
+    #[test]
+    fn csv_trace() {
+        assert_eq!(
+            trace().to_csv(),
+            "kind,start_x,start_y,end_x,end_y,colour,pen,heading,line\nmove,0,0,0,10.5,7,down,0,3\narc,0,20.5,10,10.5,1,up,90,\n"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::display::{DrawCommand, Style};
+use crate::location::Location;
+
+/// Maps where the turtle thinks it is onto the canvas: scaled about the turtle's origin,
+/// rotated clockwise, then moved so the turtle's origin lands on `origin`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub scale: f64,
+    /// Clockwise degrees.
+    pub rotation: f64,
+    pub origin: Location,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            scale: 1f64,
+            rotation: 0f64,
+            origin: Location::origin(),
+        }
+    }
+}
+
+impl Transform {
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::default()
+    }
+
+    pub fn location(&self, location: &Location) -> Location {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (x, y) = (location.x() * self.scale, location.y() * self.scale);
+        Location::new(
+            self.origin.x() + x * cos + y * sin,
+            self.origin.y() - x * sin + y * cos,
+        )
+    }
+
+    /// Where the turtle thinks a point on the canvas is, undoing `location`.
+    pub fn inverse(&self, location: &Location) -> Location {
+        if self.is_identity() {
+            return location.clone();
+        }
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (x, y) = (
+            location.x() - self.origin.x(),
+            location.y() - self.origin.y(),
+        );
+        Location::new(
+            (x * cos - y * sin) / self.scale,
+            (x * sin + y * cos) / self.scale,
+        )
+    }
+
+    /// The command as drawn on the canvas. Pen widths stay the same at any scale, but
+    /// dashes are scaled along with the lines they break up, so that the turtle's place in
+    /// the pattern still lines up with the line before.
+    pub fn command(&self, command: DrawCommand) -> DrawCommand {
+        if self.is_identity() {
+            return command;
+        }
+        match command {
+            DrawCommand::Line { start, end, style } => DrawCommand::Line {
+                start: self.location(&start),
+                end: self.location(&end),
+                style: self.style(style),
+            },
+            DrawCommand::Arc {
+                centre,
+                radius,
+                start,
+                sweep,
+                style,
+            } => DrawCommand::Arc {
+                centre: self.location(&centre),
+                radius: radius * self.scale,
+                start: start + self.rotation,
+                sweep,
+                style: self.style(style),
+            },
+            DrawCommand::Fill { points, colour } => DrawCommand::Fill {
+                points: points.iter().map(|point| self.location(point)).collect(),
+                colour,
+            },
+            DrawCommand::Text {
+                position,
+                text,
+                style,
+            } => DrawCommand::Text {
+                position: self.location(&position),
+                text,
+                style,
+            },
+        }
+    }
+
+    fn style(&self, mut style: Style) -> Style {
+        if let Some(dash) = &mut style.dash {
+            for length in &mut dash.pattern {
+                *length *= self.scale;
+            }
+            dash.offset *= self.scale;
+        }
+        style
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::fixtures::style;
+    use crate::display::Dash;
+
+    fn close(a: &Location, b: &Location) -> bool {
+        (a.x() - b.x()).abs() < 1e-9 && (a.y() - b.y()).abs() < 1e-9
+    }
+
+    #[test]
+    fn identity_leaves_points() {
+        let point = Location::new(3.0, -4.0);
+        assert_eq!(Transform::default().location(&point), point);
+    }
+
+    #[test]
+    fn scale_rotate_then_move() {
+        let transform = Transform {
+            scale: 2.0,
+            rotation: 90.0,
+            origin: Location::new(10.0, 0.0),
+        };
+        // Straight up, doubled, turned to face east, then moved along.
+        let point = transform.location(&Location::new(0.0, 5.0));
+        assert!(close(&point, &Location::new(20.0, 0.0)));
+    }
+
+    #[test]
+    fn inverse_undoes_location() {
+        let transform = Transform {
+            scale: 2.0,
+            rotation: 30.0,
+            origin: Location::new(10.0, -3.0),
+        };
+        let point = Location::new(3.0, -4.0);
+        assert!(close(
+            &transform.inverse(&transform.location(&point)),
+            &point
+        ));
+    }
+
+    #[test]
+    fn arc_radius_and_start() {
+        let transform = Transform {
+            scale: 3.0,
+            rotation: 45.0,
+            origin: Location::origin(),
+        };
+        let arc = transform.command(DrawCommand::Arc {
+            centre: Location::origin(),
+            radius: 2.0,
+            start: 10.0,
+            sweep: -90.0,
+            style: style(1),
+        });
+        match arc {
+            DrawCommand::Arc {
+                radius,
+                start,
+                sweep,
+                style,
+                ..
+            } => {
+                assert_eq!((radius, start, sweep), (6.0, 55.0, -90.0));
+                assert_eq!(style.width, 1.0);
+            }
+            _ => panic!("expected an arc"),
+        }
+    }
+
+    #[test]
+    fn dashes_scale() {
+        let transform = Transform {
+            scale: 2f64,
+            ..Transform::default()
+        };
+        let dash = Dash {
+            pattern: vec![4f64, 2f64],
+            offset: 3f64,
+        };
+        let line = transform.command(DrawCommand::Line {
+            start: Location::origin(),
+            end: Location::new(1f64, 0f64),
+            style: Style {
+                dash: Some(dash),
+                ..style(1)
+            },
+        });
+        match line {
+            DrawCommand::Line { style, .. } => {
+                let dash = style.dash.unwrap();
+                assert_eq!(dash.pattern, vec![8f64, 4f64]);
+                assert_eq!(dash.offset, 6f64);
+            }
+            _ => panic!("expected a line"),
+        }
+    }
+}
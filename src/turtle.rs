@@ -1,89 +1,565 @@
+use crate::display::{palette, Dash, DisplayList, DrawCommand, Ink, Pose, Style};
 use crate::location::Location;
-use unsvg::Image;
+use crate::trace::{EventKind, Trace, TraceEvent};
+use crate::transform::Transform;
 use unsvg::COLORS;
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Mode {
     PenUp,
     PenDown,
+    /// Draws by rubbing out what is underneath.
+    PenErase,
+    /// Draws by inverting what is underneath.
+    PenReverse,
+}
+
+/// How headings are measured.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum AngleConvention {
+    /// Logo compass headings, 0 is north and angles increase clockwise.
+    Compass,
+    /// Mathematical angles, 0 is east and angles increase counter-clockwise.
+    Math,
+}
+
+/// What happens when the turtle reaches the edge of the canvas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary {
+    /// Leave one edge and come back in from the opposite one.
+    Wrap,
+    /// Refuse to move past an edge.
+    Fence,
+    /// Move freely; anything off the canvas is clipped when drawn.
+    Window,
+}
+
+/// What PUSHSTATE saves and POPSTATE puts back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurtleState {
+    pub position: Location,
+    pub angle: f64,
+    pub colour: f32,
+    pub mode: Mode,
+    pub pen_size: f64,
+    pub dash: Vec<f64>,
 }
 
 #[derive(Debug)]
 pub struct Turtle {
     pub position: Location,
-    pub angle: f32,
+    pub angle: f64,
     pub colour: f32,
     pub mode: Mode,
+    pub pen_size: f64,
+    pub convention: AngleConvention,
+    pub boundary: Boundary,
+    /// Half the width and height of the area the turtle moves in, or `None` if it has no
+    /// edges.
+    pub bounds: Option<(f64, f64)>,
+    /// Corners of the polygon being filled, if a fill has been started.
+    pub fill: Option<Vec<Location>>,
+    /// The source line of the command being run, recorded in the trace.
+    pub line: Option<usize>,
+    /// Everything the turtle has done so far.
+    pub trace: Trace,
+    /// Whether the turtle itself is drawn, on the finished image and in animation frames.
+    pub visible: bool,
+    /// Which turtle this is, to tell its poses in the drawing from the others'.
+    pub id: usize,
+    /// States saved by PUSHSTATE, most recent last.
+    pub saved: Vec<TurtleState>,
+    /// Whether SETX, SETY and SETPOS jump without drawing even with the pen down, as they
+    /// used to.
+    pub teleport: bool,
+    /// Lengths of dash and gap the pen draws with, or empty for solid lines.
+    pub dash: Vec<f64>,
+    /// How far into the dash pattern the next line starts, so that lines drawn one after
+    /// another keep the dashes going.
+    pub dash_phase: f64,
+}
+impl Default for Turtle {
+    fn default() -> Self {
+        Turtle::with_convention(AngleConvention::Compass)
+    }
 }
+
 impl Turtle {
     pub fn new() -> Self {
+        Turtle::with_convention(AngleConvention::Compass)
+    }
+
+    pub fn with_convention(convention: AngleConvention) -> Self {
         Turtle {
             position: Location::origin(),
-            angle: 0f32,
+            angle: 0f64,
             colour: 7f32,
             mode: Mode::PenUp,
+            pen_size: 1f64,
+            convention,
+            boundary: Boundary::Window,
+            bounds: None,
+            fill: None,
+            line: None,
+            trace: Trace::new(),
+            visible: false,
+            id: 0,
+            saved: Vec::new(),
+            teleport: false,
+            dash: Vec::new(),
+            dash_phase: 0f64,
         }
     }
 }
 
 impl Turtle {
-    fn pen_up(&mut self) {
+    pub fn pen_up(&mut self) {
         self.mode = Mode::PenUp;
     }
 
-    fn pen_down(&mut self) {
+    pub fn pen_down(&mut self) {
         self.mode = Mode::PenDown;
     }
 
-    fn move_turtle(&mut self, numpixels: f32, image: &mut Image) -> Result<(), String> {
+    pub fn pen_erase(&mut self) {
+        self.mode = Mode::PenErase;
+    }
+
+    pub fn pen_reverse(&mut self) {
+        self.mode = Mode::PenReverse;
+    }
+
+    /// What PENMODE reports.
+    pub fn pen_mode(&self) -> &'static str {
         match self.mode {
-            Mode::PenUp => {
-                self.pen_up_move(numpixels);
-                Ok(())
+            Mode::PenUp | Mode::PenDown => "PAINT",
+            Mode::PenErase => "ERASE",
+            Mode::PenReverse => "REVERSE",
+        }
+    }
+
+    /// Sets the dash pattern from SETPENSTYLE: "solid, "dash, "dot, or the lengths of dash
+    /// and gap to alternate between.
+    pub fn set_pen_style(&mut self, style: &str) -> Result<(), String> {
+        let dash = match style.to_ascii_lowercase().as_str() {
+            "solid" => Vec::new(),
+            "dash" => vec![8f64, 4f64],
+            "dot" => vec![1f64, 3f64],
+            _ => {
+                let lengths = style
+                    .split_whitespace()
+                    .map(|length| length.parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|_| format!("Unknown pen style {style}"))?;
+                if lengths.is_empty()
+                    || lengths
+                        .iter()
+                        .any(|length| !length.is_finite() || *length < 0f64)
+                    || lengths.iter().sum::<f64>() == 0f64
+                {
+                    return Err(format!("Unknown pen style {style}"));
+                }
+                // An odd pattern swaps dashes and gaps on its second time through, as svg
+                // does, so spell that out to keep the phase between moves right.
+                if lengths.len() % 2 == 1 {
+                    lengths.repeat(2)
+                } else {
+                    lengths
+                }
+            }
+        };
+        self.dash = dash;
+        self.dash_phase = 0f64;
+        Ok(())
+    }
+
+    pub fn move_turtle(&mut self, numpixels: f64, drawing: &mut DisplayList) -> Result<(), String> {
+        let end = self.destination(self.angle, numpixels);
+        self.move_to(end, drawing)
+    }
+
+    /// Moves `numpixels` to the turtle's right, or its left if negative, without turning.
+    pub fn move_sideways(
+        &mut self,
+        numpixels: f64,
+        drawing: &mut DisplayList,
+    ) -> Result<(), String> {
+        let right = match self.convention {
+            AngleConvention::Compass => self.angle + 90f64,
+            AngleConvention::Math => self.angle - 90f64,
+        };
+        let end = self.destination(right, numpixels);
+        self.move_to(end, drawing)
+    }
+
+    /// Moves the turtle to `end` the way the canvas edges allow, drawing if the pen is down.
+    /// The edges are where the canvas ends, so they are checked after the drawing's
+    /// transform has put the turtle's points onto the canvas.
+    fn move_to(&mut self, end: Location, drawing: &mut DisplayList) -> Result<(), String> {
+        // A canvas that grows to fit the drawing has no edges to wrap around or stop at.
+        let (half_width, half_height) = match self.bounds {
+            Some(extents) => extents,
+            None => return self.travel_to(end, drawing),
+        };
+        let transform = drawing.transform().clone();
+        match self.boundary {
+            Boundary::Window => self.travel_to(end, drawing),
+            Boundary::Fence => {
+                let from = transform.location(&self.position);
+                let to = transform.location(&end);
+                if to.x().abs() > half_width || to.y().abs() > half_height {
+                    let crossing = boundary_crossing(&from, &to, half_width, half_height);
+                    let hit = round_location(&transform.inverse(&crossing));
+                    return Err(format!(
+                        "The turtle hit the fence at ({}, {})",
+                        hit.x(),
+                        hit.y()
+                    ));
+                }
+                self.travel_to(end, drawing)
             }
-            Mode::PenDown => self.pen_down_move(numpixels, image),
+            Boundary::Wrap => self.wrap_to(end, half_width, half_height, &transform, drawing),
         }
     }
 
-    fn pen_up_move(&mut self, numpixels: f32) {
-        let angle = self.angle.to_radians();
-        let x = (1000f32 * numpixels * angle.sin()).round() / 1000f32;
-        let y = (1000f32 * numpixels * angle.cos()).round() / 1000f32;
+    /// Where the turtle ends up after moving `numpixels` along the heading `angle`, rounded
+    /// to 3 decimal places so pen up and pen down moves land on the same points.
+    fn destination(&self, angle: f64, numpixels: f64) -> Location {
+        let angle = angle.to_radians();
+        let (dx, dy) = match self.convention {
+            AngleConvention::Compass => (angle.sin(), angle.cos()),
+            AngleConvention::Math => (angle.cos(), angle.sin()),
+        };
+        let x = (1000f64 * numpixels * dx).round() / 1000f64;
+        let y = (1000f64 * numpixels * dy).round() / 1000f64;
         let translation_vector = Location::new(x, y);
-        self.position = self.position.translate(&translation_vector);
-    }
-
-    fn pen_down_move(&mut self, numpixels: f32, image: &mut Image) -> Result<(), String> {
-        let (x, y) = match image.draw_simple_line(
-            self.position.x() + ((image.get_dimensions().0 / 2) as f32),
-            ((image.get_dimensions().1 as f32) - self.position.y())
-                - ((image.get_dimensions().1 / 2) as f32),
-            self.angle as i32,
-            numpixels,
-            COLORS[self.colour as usize],
-        ) {
-            Ok(tup) => tup,
-            Err(error) => {
-                return Err(error.to_string());
+        round_location(&self.position.translate(&translation_vector))
+    }
+
+    /// Moves the turtle in a straight line to `end`, drawing if the pen is down.
+    fn travel_to(&mut self, end: Location, drawing: &mut DisplayList) -> Result<(), String> {
+        if self.is_drawing() && self.position != end {
+            let line = DrawCommand::Line {
+                start: self.position.clone(),
+                end: end.clone(),
+                style: Style {
+                    dash: self.dash_from(self.dash_phase),
+                    ..self.style()
+                },
+            };
+            if !self.dash.is_empty() {
+                let period: f64 = self.dash.iter().sum();
+                self.dash_phase = (self.dash_phase + line.length()) % period;
             }
+            drawing.push(line);
+        } else if !self.is_drawing() {
+            // Dashes start afresh once the pen has been lifted.
+            self.dash_phase = 0f64;
+        }
+        if let Some(points) = &mut self.fill {
+            points.push(end.clone());
+        }
+        self.record(EventKind::Move, self.position.clone(), end.clone());
+        self.position = end;
+        self.record_pose(drawing);
+        Ok(())
+    }
+
+    /// Notes in the drawing where the turtle is now, for animations to show it there.
+    pub fn record_pose(&self, drawing: &mut DisplayList) {
+        drawing.set_pose(self.id, self.pose());
+    }
+
+    /// Adds an event to the trace with the turtle's current state.
+    fn record(&mut self, kind: EventKind, start: Location, end: Location) {
+        self.trace.push(TraceEvent {
+            kind,
+            start,
+            end,
+            colour: self.colour,
+            mode: self.mode,
+            heading: self.heading(),
+            line: self.line,
+        });
+    }
+
+    /// How the turtle's pen currently draws.
+    fn style(&self) -> Style {
+        Style {
+            colour: COLORS[self.colour as usize],
+            width: self.pen_size,
+            ink: match self.mode {
+                Mode::PenErase => Ink::Erase,
+                Mode::PenReverse => Ink::Reverse,
+                Mode::PenUp | Mode::PenDown => Ink::Paint,
+            },
+            dash: self.dash_from(0f64),
+        }
+    }
+
+    /// The pen's dashes starting `offset` into the pattern, or `None` if it draws solid.
+    fn dash_from(&self, offset: f64) -> Option<Dash> {
+        if self.dash.is_empty() {
+            return None;
+        }
+        Some(Dash {
+            pattern: self.dash.clone(),
+            offset,
+        })
+    }
+
+    /// Whether moving leaves a mark, in any pen mode.
+    fn is_drawing(&self) -> bool {
+        !matches!(self.mode, Mode::PenUp)
+    }
+
+    /// The heading as a compass bearing, whatever convention the turtle uses.
+    fn compass_heading(&self) -> f64 {
+        match self.convention {
+            AngleConvention::Compass => self.angle,
+            AngleConvention::Math => (90f64 - self.angle).rem_euclid(360f64),
+        }
+    }
+
+    /// Draws an arc of a circle centred on the turtle, starting straight ahead and turning
+    /// `degrees` the same way TURN would. The turtle doesn't move.
+    pub fn arc(&mut self, degrees: f64, radius: f64, drawing: &mut DisplayList) {
+        let sweep = match self.convention {
+            AngleConvention::Compass => degrees,
+            AngleConvention::Math => -degrees,
+        };
+        let start = self.compass_heading();
+        if self.is_drawing() {
+            drawing.push(DrawCommand::Arc {
+                centre: self.position.clone(),
+                radius,
+                start,
+                sweep,
+                style: self.style(),
+            });
+        }
+        let point = |heading: f64| {
+            let heading = heading.to_radians();
+            round_location(&Location::new(
+                self.position.x() + radius * heading.sin(),
+                self.position.y() + radius * heading.cos(),
+            ))
         };
-        let x = x - ((image.get_dimensions().0 / 2) as f32);
-        let y = ((image.get_dimensions().1 / 2) as f32) - y;
+        self.record(EventKind::Arc, point(start), point(start + sweep));
+        self.record_pose(drawing);
+    }
+
+    /// Writes `text` at the turtle's position.
+    pub fn label(&mut self, text: String, drawing: &mut DisplayList) {
+        self.record(
+            EventKind::Label,
+            self.position.clone(),
+            self.position.clone(),
+        );
+        drawing.push(DrawCommand::Text {
+            position: self.position.clone(),
+            text,
+            style: self.style(),
+        });
+        self.record_pose(drawing);
+    }
 
-        let x = (1000f32 * x).round() / 1000f32;
-        let y = (1000f32 * y).round() / 1000f32;
-        self.position = Location::new(x, y);
+    /// Starts recording the turtle's path as the outline of a filled shape.
+    pub fn begin_fill(&mut self) {
+        self.fill = Some(vec![self.position.clone()]);
+    }
+
+    /// Fills the shape traced since `begin_fill` in the pen colour.
+    pub fn end_fill(&mut self, drawing: &mut DisplayList) -> Result<(), String> {
+        let points = match self.fill.take() {
+            Some(points) => points,
+            None => return Err(String::from("There is no fill to end")),
+        };
+        let start = points[0].clone();
+        self.record(EventKind::Fill, start, self.position.clone());
+        if points.len() >= 3 {
+            drawing.push(DrawCommand::Fill {
+                points,
+                colour: COLORS[self.colour as usize],
+            });
+        }
+        self.record_pose(drawing);
         Ok(())
     }
 
-    fn turn(&mut self, degrees: f32) -> Result<(), String> {
+    /// Moves towards `end` on a torus, splitting the line each time it crosses an edge and
+    /// carrying on from the opposite edge. The wrapping happens on the canvas, through
+    /// `transform`.
+    fn wrap_to(
+        &mut self,
+        end: Location,
+        half_width: f64,
+        half_height: f64,
+        transform: &Transform,
+        drawing: &mut DisplayList,
+    ) -> Result<(), String> {
+        if half_width == 0f64 || half_height == 0f64 {
+            return Err(String::from("The canvas is too small to wrap around"));
+        }
+        let wrap = |value: f64, limit: f64| (value + limit).rem_euclid(2f64 * limit) - limit;
+        let from = transform.location(&self.position);
+        let to = transform.location(&end);
+        let mut dx = to.x() - from.x();
+        let mut dy = to.y() - from.y();
+        let mut from = Location::new(wrap(from.x(), half_width), wrap(from.y(), half_height));
+        self.position = transform.inverse(&from);
+        loop {
+            let to = Location::new(from.x() + dx, from.y() + dy);
+            let (t, hit_x, hit_y) = edge_fraction(&from, &to, half_width, half_height);
+            if t >= 1f64 {
+                return self.travel_to(round_location(&transform.inverse(&to)), drawing);
+            }
+            let crossing = Location::new(from.x() + t * dx, from.y() + t * dy);
+            self.travel_to(transform.inverse(&crossing), drawing)?;
+            let x = if hit_x {
+                -dx.signum() * half_width
+            } else {
+                crossing.x()
+            };
+            let y = if hit_y {
+                -dy.signum() * half_height
+            } else {
+                crossing.y()
+            };
+            from = Location::new(x, y);
+            self.position = transform.inverse(&from);
+            dx *= 1f64 - t;
+            dy *= 1f64 - t;
+        }
+    }
+
+    pub fn turn(&mut self, degrees: f64) -> Result<(), String> {
         self.set_heading(self.angle + degrees)
     }
 
-    fn set_heading(&mut self, degrees: f32) -> Result<(), String> {
-        if degrees.fract() > 0f32 {
-            return Err(String::from("The angle must be an integer"));
+    pub fn set_heading(&mut self, degrees: f64) -> Result<(), String> {
+        if !degrees.is_finite() {
+            return Err(String::from("The angle must be a finite number"));
+        }
+        self.angle = degrees.rem_euclid(360f64);
+        // rem_euclid can round up to exactly 360 for tiny negative angles.
+        if self.angle >= 360f64 {
+            self.angle = 0f64;
+        }
+        Ok(())
+    }
+
+    /// The heading reported by HEADING, always in [0, 360) in the turtle's convention.
+    pub fn heading(&self) -> f64 {
+        self.angle
+    }
+
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+    }
+
+    /// Gives the turtle edges for a `width` by `height` canvas centred on the origin.
+    pub fn set_bounds(&mut self, half_width: f64, half_height: f64) {
+        self.bounds = Some((half_width, half_height));
+    }
+
+    pub fn set_pen_size(&mut self, size: f64) -> Result<(), String> {
+        if !(size.is_finite() && size >= 0f64) {
+            return Err(String::from("The pen size must be a positive number"));
+        }
+        self.pen_size = size;
+        Ok(())
+    }
+
+    pub fn push_state(&mut self) {
+        self.saved.push(TurtleState {
+            position: self.position.clone(),
+            angle: self.angle,
+            colour: self.colour,
+            mode: self.mode,
+            pen_size: self.pen_size,
+            dash: self.dash.clone(),
+        });
+    }
+
+    /// Puts back the last state PUSHSTATE saved. The turtle jumps back without drawing.
+    pub fn pop_state(&mut self) -> Result<(), String> {
+        let state = match self.saved.pop() {
+            Some(state) => state,
+            None => return Err(String::from("There is no saved state to pop")),
+        };
+        self.position = state.position;
+        self.angle = state.angle;
+        self.colour = state.colour;
+        self.mode = state.mode;
+        self.pen_size = state.pen_size;
+        self.dash = state.dash;
+        self.dash_phase = 0f64;
+        Ok(())
+    }
+
+    /// Draws expanded L-system `symbols`: F and G draw forward, f moves forward without
+    /// drawing, + turns left and - turns right by `angle`, and [ and ] push and pop the
+    /// turtle's state. Anything else is skipped.
+    pub fn run_lsystem(
+        &mut self,
+        symbols: &str,
+        step: f64,
+        angle: f64,
+        drawing: &mut DisplayList,
+    ) -> Result<(), String> {
+        // Left is a negative turn for compass headings and a positive one in maths.
+        let left = match self.convention {
+            AngleConvention::Compass => -angle,
+            AngleConvention::Math => angle,
+        };
+        for symbol in symbols.chars() {
+            match symbol {
+                'F' | 'G' => self.move_turtle(step, drawing)?,
+                'f' => {
+                    let mode = self.mode;
+                    self.pen_up();
+                    self.move_turtle(step, drawing)?;
+                    self.mode = mode;
+                }
+                '+' => self.turn(left)?,
+                '-' => self.turn(-left)?,
+                '[' => self.push_state(),
+                ']' => self.pop_state()?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn show_turtle(&mut self, drawing: &mut DisplayList) {
+        self.visible = true;
+        self.record_pose(drawing);
+    }
+
+    pub fn hide_turtle(&mut self, drawing: &mut DisplayList) {
+        self.visible = false;
+        self.record_pose(drawing);
+    }
+
+    /// Where the turtle is, where it faces, its pen colour and whether it is showing.
+    pub fn pose(&self) -> Pose {
+        Pose {
+            position: self.position.clone(),
+            heading: self.compass_heading(),
+            colour: COLORS[self.colour as usize],
+            visible: self.visible,
+        }
+    }
+
+    /// Sets the pen to a palette colour, as SETPENCOLOR does.
+    pub fn set_pen_colour(&mut self, colourcode: f32) -> Result<(), String> {
+        if palette(colourcode).is_none() {
+            return Err(format!(
+                "The pen colour must be a whole number from 0 to {}",
+                COLORS.len() - 1
+            ));
         }
-        self.angle = degrees;
+        self.change_colour(colourcode);
         Ok(())
     }
 
@@ -91,15 +567,110 @@ impl Turtle {
         self.colour = colourcode;
     }
 
-    fn set_x(&mut self, numpixels: f32) {
-        self.position = Location::new(numpixels, self.position.y());
+    pub fn set_x(&mut self, numpixels: f64, drawing: &mut DisplayList) -> Result<(), String> {
+        self.set_pos(numpixels, self.position.y(), drawing)
+    }
+
+    pub fn set_y(&mut self, numpixels: f64, drawing: &mut DisplayList) -> Result<(), String> {
+        self.set_pos(self.position.x(), numpixels, drawing)
+    }
+
+    /// Moves straight to `x`, `y`, drawing if the pen is down unless the turtle teleports.
+    pub fn set_pos(&mut self, x: f64, y: f64, drawing: &mut DisplayList) -> Result<(), String> {
+        let end = round_location(&Location::new(x, y));
+        if self.teleport {
+            // Nothing is drawn, but the jump still counts towards fills and the trace.
+            self.dash_phase = 0f64;
+            if let Some(points) = &mut self.fill {
+                points.push(end.clone());
+            }
+            self.record(EventKind::Move, self.position.clone(), end.clone());
+            self.position = end;
+            self.record_pose(drawing);
+            return Ok(());
+        }
+        self.move_to(end, drawing)
+    }
+
+    /// Goes back to the origin facing the starting heading.
+    pub fn home(&mut self, drawing: &mut DisplayList) -> Result<(), String> {
+        self.set_pos(0f64, 0f64, drawing)?;
+        self.set_heading(0f64)
+    }
+
+    /// The position as the list POS reports.
+    pub fn pos(&self) -> Vec<f64> {
+        vec![self.position.x(), self.position.y()]
+    }
+
+    /// The heading that would point the turtle at `target`, in its angle convention. Any
+    /// heading points at the turtle's own position, so that keeps the current one.
+    pub fn towards(&self, target: &Location) -> f64 {
+        if *target == self.position {
+            return self.heading();
+        }
+        let angle = (target - &self.position).angle();
+        let heading = match self.convention {
+            AngleConvention::Compass => 90f64 - angle,
+            AngleConvention::Math => angle,
+        };
+        let heading = heading.rem_euclid(360f64);
+        if heading >= 360f64 {
+            0f64
+        } else {
+            heading
+        }
     }
 
-    fn set_y(&mut self, numpixels: f32) {
-        self.position = Location::new(self.position.x(), numpixels);
+    pub fn distance(&self, target: &Location) -> f64 {
+        (target - &self.position).length()
     }
 }
 
+/// How far along the line from `from` to `to` it first touches the canvas edge, as a
+/// fraction of the line, and whether that is a vertical and/or horizontal edge.
+fn edge_fraction(
+    from: &Location,
+    to: &Location,
+    half_width: f64,
+    half_height: f64,
+) -> (f64, bool, bool) {
+    let fraction = |from: f64, to: f64, limit: f64| {
+        if to > from {
+            (limit - from) / (to - from)
+        } else if to < from {
+            (-limit - from) / (to - from)
+        } else {
+            f64::INFINITY
+        }
+    };
+    let tx = fraction(from.x(), to.x(), half_width);
+    let ty = fraction(from.y(), to.y(), half_height);
+    let t = tx.min(ty);
+    (t, tx <= t, ty <= t)
+}
+
+fn boundary_crossing(
+    from: &Location,
+    to: &Location,
+    half_width: f64,
+    half_height: f64,
+) -> Location {
+    let (t, _, _) = edge_fraction(from, to, half_width, half_height);
+    let t = t.clamp(0f64, 1f64);
+    Location::new(
+        from.x() + t * (to.x() - from.x()),
+        from.y() + t * (to.y() - from.y()),
+    )
+}
+
+fn round_location(location: &Location) -> Location {
+    Location::new(
+        (1000f64 * location.x()).round() / 1000f64,
+        (1000f64 * location.y()).round() / 1000f64,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     // Some of the tests ignoring result from error able functions because the error is not related
@@ -108,120 +679,152 @@ mod tests {
     // functions to ensure they catch errors. Therefore i can ensure that these errors can be
     // ignored in those tests.
     use super::*;
+    use crate::display::fixtures::style;
     #[test]
     fn setting_x_position() {
         let mut turtle = Turtle::new();
-        turtle.set_x(10f32);
-        assert_eq!(turtle.position, Location::new(10f32, 0f32));
+        let _ = turtle.set_x(10f64, &mut DisplayList::new());
+        assert_eq!(turtle.position, Location::new(10f64, 0f64));
     }
 
     #[test]
     fn setting_y_position() {
         let mut turtle = Turtle::new();
-        turtle.set_y(10f32);
-        assert_eq!(turtle.position, Location::new(0f32, 10f32));
+        let _ = turtle.set_y(10f64, &mut DisplayList::new());
+        assert_eq!(turtle.position, Location::new(0f64, 10f64));
     }
 
     #[test]
     fn set_heading_valid_small() {
         let mut turtle = Turtle::new();
-        let _ = turtle.set_heading(90f32);
-        let _ = turtle.set_heading(45f32);
-        assert_eq!(turtle.angle, 45f32);
+        let _ = turtle.set_heading(90f64);
+        let _ = turtle.set_heading(45f64);
+        assert_eq!(turtle.angle, 45f64);
     }
 
     #[test]
     fn set_heading_valid_large() {
         let mut turtle = Turtle::new();
-        let _ = turtle.set_heading(90f32);
-        let _ = turtle.set_heading(370f32);
-        assert_eq!(turtle.angle, 370f32);
+        let _ = turtle.set_heading(90f64);
+        let _ = turtle.set_heading(370f64);
+        assert_eq!(turtle.angle, 10f64);
+    }
+
+    #[test]
+    fn set_heading_negative() {
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_heading(-15f64);
+        assert_eq!(turtle.heading(), 345f64);
+    }
+
+    #[test]
+    fn set_heading_tiny_negative() {
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_heading(-1e-14);
+        assert!(turtle.heading() >= 0f64 && turtle.heading() < 360f64);
+    }
+
+    #[test]
+    fn set_heading_fractional() {
+        let mut turtle = Turtle::new();
+        let res = turtle.set_heading(137.5);
+        assert!(res.is_ok());
+        assert_eq!(turtle.angle, 137.5);
     }
 
     #[test]
     fn set_heading_invalid() {
         let mut turtle = Turtle::new();
-        let res = turtle.set_heading(4.5);
-        assert_eq!(res.is_err(), true);
+        let res = turtle.set_heading(f64::NAN);
+        assert!(res.is_err());
     }
 
     #[test]
     fn turn_turtle_valid_small() {
         let mut turtle = Turtle::new();
-        let _ = turtle.set_heading(45f32);
-        let _ = turtle.turn(90f32);
-        assert_eq!(turtle.angle, 135f32);
+        let _ = turtle.set_heading(45f64);
+        let _ = turtle.turn(90f64);
+        assert_eq!(turtle.angle, 135f64);
     }
 
     #[test]
     fn turn_turtle_valid_big() {
         let mut turtle = Turtle::new();
-        let _ = turtle.set_heading(10f32);
-        let _ = turtle.turn(360f32);
-        assert_eq!(turtle.angle, 370f32);
+        let _ = turtle.set_heading(10f64);
+        let _ = turtle.turn(360f64);
+        assert_eq!(turtle.angle, 10f64);
     }
 
     #[test]
-    fn turn_turtle_invalid() {
+    fn turn_turtle_fractional() {
         let mut turtle = Turtle::new();
+        let _ = turtle.set_heading(45f64);
         let res = turtle.turn(90.5);
-        assert_eq!(res.is_err(), true);
+        assert!(res.is_ok());
+        assert_eq!(turtle.angle, 135.5);
+    }
+
+    #[test]
+    fn turn_turtle_invalid() {
+        let mut turtle = Turtle::new();
+        let res = turtle.turn(f64::INFINITY);
+        assert!(res.is_err());
     }
 
     #[test]
     fn move_turtle_forward() {
-        let mut image = Image::new(64, 64);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
-        match turtle.move_turtle(2f32, &mut image) {
+        match turtle.move_turtle(2f64, &mut drawing) {
             Ok(_) => (),
             Err(_) => panic!("error drawing on image, shouldnt be drawing image though"),
         }
-        assert_eq!(turtle.position, Location::new(0f32, 2f32));
+        assert_eq!(turtle.position, Location::new(0f64, 2f64));
     }
 
     #[test]
     fn move_turtle_back() {
-        let mut image = Image::new(64, 64);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
-        let _ = turtle.turn(180f32); // TODO explain why error ignored.
-        match turtle.move_turtle(2f32, &mut image) {
+        let _ = turtle.turn(180f64); // TODO explain why error ignored.
+        match turtle.move_turtle(2f64, &mut drawing) {
             Ok(_) => (),
             Err(_) => panic!("error drawing on image, shouldnt be drawing image though"),
         }
 
-        assert_eq!(turtle.position, Location::new(0f32, -2f32));
+        assert_eq!(turtle.position, Location::new(0f64, -2f64));
     }
 
     #[test]
     fn move_turtle_right() {
-        let mut image = Image::new(64, 64);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
-        let _ = turtle.turn(90f32);
-        match turtle.move_turtle(2f32, &mut image) {
+        let _ = turtle.turn(90f64);
+        match turtle.move_turtle(2f64, &mut drawing) {
             Ok(_) => (),
             Err(_) => panic!("error drawing on image, shouldnt be drawing image though"),
         }
-        assert_eq!(turtle.position, Location::new(2f32, 0f32));
+        assert_eq!(turtle.position, Location::new(2f64, 0f64));
     }
 
     #[test]
     fn move_turtle_left() {
-        let mut image = Image::new(64, 64);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
-        let _ = turtle.turn(270f32);
-        match turtle.move_turtle(2f32, &mut image) {
+        let _ = turtle.turn(270f64);
+        match turtle.move_turtle(2f64, &mut drawing) {
             Ok(_) => (),
             Err(_) => panic!("error drawing on image, shouldnt be drawing image though"),
         }
-        assert_eq!(turtle.position, Location::new(-2f32, 0f32));
+        assert_eq!(turtle.position, Location::new(-2f64, 0f64));
     }
 
     #[test]
     fn move_turtle_diagonal_small() {
-        let mut image = Image::new(64, 64);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
-        let _ = turtle.set_heading(45f32);
-        match turtle.move_turtle((2f32).sqrt(), &mut image) {
+        let _ = turtle.set_heading(45f64);
+        match turtle.move_turtle((2f64).sqrt(), &mut drawing) {
             Ok(_) => (),
             Err(_) => panic!("error drawing on image, shouldnt be drawing image though"),
         }
@@ -229,94 +832,751 @@ mod tests {
 
     #[test]
     fn move_turtle_diagonal_large() {
-        let mut image = Image::new(64, 64);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
-        let _ = turtle.set_heading(360f32 + 45f32);
-        match turtle.move_turtle((2f32).sqrt(), &mut image) {
+        let _ = turtle.set_heading(360f64 + 45f64);
+        match turtle.move_turtle((2f64).sqrt(), &mut drawing) {
             Ok(_) => (),
             Err(_) => panic!("error drawing on image, shouldnt be drawing image though"),
         }
-        assert_eq!(turtle.position, Location::new(1f32, 1f32));
+        assert_eq!(turtle.position, Location::new(1f64, 1f64));
     }
 
     #[test]
     fn draw_forward() {
-        let mut image = Image::new(256, 256);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
         turtle.pen_down();
-        let _ = turtle.move_turtle(100f32, &mut image);
+        let _ = turtle.move_turtle(100f64, &mut drawing);
         //        let _ = image.save_png("one_forward_test.png");
-        assert_eq!(turtle.position, Location::new(0f32, 100f32));
+        assert_eq!(turtle.position, Location::new(0f64, 100f64));
     }
 
     #[test]
     fn draw_left() {
-        let mut image = Image::new(256, 256);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
         turtle.pen_down();
-        let _ = turtle.turn(270f32);
-        let _ = turtle.move_turtle(100f32, &mut image);
+        let _ = turtle.turn(270f64);
+        let _ = turtle.move_turtle(100f64, &mut drawing);
         //        let _ = image.save_png("one_left_test.png");
-        assert_eq!(turtle.position, Location::new(-100f32, 0f32));
+        assert_eq!(turtle.position, Location::new(-100f64, 0f64));
     }
 
     #[test]
     fn draw_right() {
-        let mut image = Image::new(256, 256);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
         turtle.pen_down();
-        let _ = turtle.turn(90f32);
-        let _ = turtle.move_turtle(100f32, &mut image);
+        let _ = turtle.turn(90f64);
+        let _ = turtle.move_turtle(100f64, &mut drawing);
         //        let _ = image.save_png("one_right_test.png");
-        assert_eq!(turtle.position, Location::new(100f32, 0f32));
+        assert_eq!(turtle.position, Location::new(100f64, 0f64));
     }
 
     #[test]
     fn draw_back() {
-        let mut image = Image::new(256, 256);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
         turtle.pen_down();
-        let _ = turtle.turn(180f32);
-        let _ = turtle.move_turtle(100f32, &mut image);
+        let _ = turtle.turn(180f64);
+        let _ = turtle.move_turtle(100f64, &mut drawing);
         //        let _ = image.save_png("one_back_test.png");
-        assert_eq!(turtle.position, Location::new(0f32, -100f32));
+        assert_eq!(turtle.position, Location::new(0f64, -100f64));
     }
 
     #[test]
     fn draw_non_origin() {
-        let mut image = Image::new(256, 256);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
-        let _ = turtle.set_heading(90f32);
-        let _ = turtle.move_turtle(50f32, &mut image);
-        let _ = turtle.set_heading(0f32);
+        let _ = turtle.set_heading(90f64);
+        let _ = turtle.move_turtle(50f64, &mut drawing);
+        let _ = turtle.set_heading(0f64);
         turtle.pen_down();
-        let _ = turtle.move_turtle(100f32, &mut image);
+        let _ = turtle.move_turtle(100f64, &mut drawing);
         //        let _ = image.save_png("non_origin_test.png");
-        assert_eq!(turtle.position, Location::new(50f32, 100f32));
+        assert_eq!(turtle.position, Location::new(50f64, 100f64));
     }
 
     #[test]
     fn draw_diagonal() {
-        let mut image = Image::new(256, 256);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
         turtle.pen_down();
-        let _ = turtle.set_heading(45f32);
-        let _ = turtle.move_turtle(100f32, &mut image);
+        let _ = turtle.set_heading(45f64);
+        let _ = turtle.move_turtle(100f64, &mut drawing);
         //        let _ = image.save_png("one_diagonal_test.png");
         assert_eq!(turtle.position, Location::new(70.711, 70.711));
     }
 
     #[test]
     fn draw_two_lines() {
-        let mut image = Image::new(256, 256);
+        let mut drawing = DisplayList::new();
         let mut turtle = Turtle::new();
         turtle.pen_down();
-        let _ = turtle.move_turtle(50f32, &mut image);
+        let _ = turtle.move_turtle(50f64, &mut drawing);
         turtle.pen_up();
-        let _ = turtle.move_turtle(20f32, &mut image);
+        let _ = turtle.move_turtle(20f64, &mut drawing);
         turtle.pen_down();
-        let _ = turtle.move_turtle(50f32, &mut image);
+        let _ = turtle.move_turtle(50f64, &mut drawing);
         //        let _ = image.save_png("two_lines_test.png");
-        assert_eq!(turtle.position, Location::new(0f32, 120f32));
+        assert_eq!(turtle.position, Location::new(0f64, 120f64));
+    }
+
+    #[test]
+    fn draw_fractional_heading() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.pen_down();
+        let _ = turtle.set_heading(137.5);
+        let res = turtle.move_turtle(100f64, &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(67.559, -73.728));
+    }
+
+    #[test]
+    fn pen_up_and_pen_down_land_together() {
+        let mut drawing = DisplayList::new();
+        let mut pen = Turtle::new();
+        let mut moving = Turtle::new();
+        pen.pen_down();
+        let _ = pen.set_heading(33.3);
+        let _ = moving.set_heading(33.3);
+        let _ = pen.move_turtle(77.7, &mut drawing);
+        let _ = moving.move_turtle(77.7, &mut drawing);
+        assert_eq!(pen.position, moving.position);
+    }
+
+    #[test]
+    fn math_convention_starts_east() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::with_convention(AngleConvention::Math);
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        assert_eq!(turtle.position, Location::new(10f64, 0f64));
+    }
+
+    #[test]
+    fn math_convention_turns_counter_clockwise() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::with_convention(AngleConvention::Math);
+        turtle.pen_down();
+        let _ = turtle.turn(90f64);
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        assert_eq!(turtle.position, Location::new(0f64, 10f64));
+        assert_eq!(turtle.heading(), 90f64);
+    }
+
+    #[test]
+    fn window_moves_off_canvas() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.pen_down();
+        let res = turtle.move_turtle(100f64, &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(0f64, 100f64));
+    }
+
+    #[test]
+    fn fence_stops_at_edge() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Fence);
+        turtle.pen_down();
+        let res = turtle.move_turtle(100f64, &mut drawing);
+        assert_eq!(
+            res,
+            Err(String::from("The turtle hit the fence at (0, 32)"))
+        );
+        assert_eq!(turtle.position, Location::origin());
+    }
+
+    #[test]
+    fn fence_allows_moves_inside() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Fence);
+        let res = turtle.move_turtle(32f64, &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(0f64, 32f64));
+    }
+
+    #[test]
+    fn wrap_comes_back_from_opposite_edge() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Wrap);
+        turtle.pen_down();
+        let res = turtle.move_turtle(40f64, &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(0f64, -24f64));
+    }
+
+    #[test]
+    fn wrap_diagonal_through_corner() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Wrap);
+        let _ = turtle.set_heading(45f64);
+        let res = turtle.move_turtle(64f64 * 2f64.sqrt(), &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(0f64, 0f64));
+    }
+
+    #[test]
+    fn wrap_several_times() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Wrap);
+        let _ = turtle.set_heading(90f64);
+        let _ = turtle.move_turtle(200f64, &mut drawing);
+        assert_eq!(turtle.position, Location::new(8f64, 0f64));
+    }
+
+    #[test]
+    fn fence_is_on_the_canvas_after_scaling() {
+        let mut drawing = DisplayList::new();
+        let _ = drawing.set_scale(2f64);
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Fence);
+        // 20 steps is 40 on the canvas, past its edge at 32.
+        assert_eq!(
+            turtle.move_turtle(20f64, &mut drawing),
+            Err(String::from("The turtle hit the fence at (0, 16)"))
+        );
+        assert!(turtle.move_turtle(16f64, &mut drawing).is_ok());
+    }
+
+    #[test]
+    fn wrap_is_on_the_canvas_after_scaling() {
+        let mut drawing = DisplayList::new();
+        let _ = drawing.set_scale(2f64);
+        drawing.set_origin(Location::new(8f64, 0f64));
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Wrap);
+        turtle.pen_down();
+        let _ = turtle.set_heading(90f64);
+        // From 8 on the canvas, 20 steps goes 40 across and comes back in at -16.
+        assert!(turtle.move_turtle(20f64, &mut drawing).is_ok());
+        assert_eq!(turtle.position, Location::new(-12f64, 0f64));
+        assert_eq!(drawing.commands().len(), 2);
+        match &drawing.commands()[0] {
+            DrawCommand::Line { start, end, .. } => {
+                assert_eq!(*start, Location::new(8f64, 0f64));
+                assert_eq!(*end, Location::new(32f64, 0f64));
+            }
+            _ => panic!("expected a line"),
+        }
+    }
+
+    #[test]
+    fn unbounded_turtle_ignores_fence() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.set_boundary(Boundary::Fence);
+        turtle.pen_down();
+        let res = turtle.move_turtle(1000f64, &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(0f64, 1000f64));
+    }
+
+    #[test]
+    fn pen_down_move_emits_line() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.pen_down();
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        assert_eq!(
+            drawing.commands(),
+            &[DrawCommand::Line {
+                start: Location::origin(),
+                end: Location::new(0f64, 10f64),
+                style: style(7),
+            }]
+        );
+    }
+
+    #[test]
+    fn pen_up_move_emits_nothing() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        assert!(drawing.is_empty());
+    }
+
+    #[test]
+    fn wrapped_line_is_split() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Wrap);
+        turtle.pen_down();
+        let _ = turtle.move_turtle(40f64, &mut drawing);
+        assert_eq!(drawing.commands().len(), 2);
+    }
+
+    #[test]
+    fn arc_starts_at_heading() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::with_convention(AngleConvention::Math);
+        turtle.pen_down();
+        turtle.arc(90f64, 10f64, &mut drawing);
+        match &drawing.commands()[0] {
+            DrawCommand::Arc { start, sweep, .. } => {
+                assert_eq!(*start, 90f64);
+                assert_eq!(*sweep, -90f64);
+            }
+            _ => panic!("expected an arc"),
+        }
+    }
+
+    #[test]
+    fn label_at_position() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_x(5f64, &mut drawing);
+        turtle.label(String::from("hello"), &mut drawing);
+        assert_eq!(
+            drawing.bounding_box(),
+            Some((Location::new(5f64, 0f64), Location::new(5f64, 0f64)))
+        );
+    }
+
+    #[test]
+    fn fill_traced_shape() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.begin_fill();
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        let _ = turtle.turn(90f64);
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        let res = turtle.end_fill(&mut drawing);
+        assert!(res.is_ok());
+        match &drawing.commands()[0] {
+            DrawCommand::Fill { points, .. } => assert_eq!(points.len(), 3),
+            _ => panic!("expected a fill"),
+        }
+    }
+
+    #[test]
+    fn end_fill_without_begin() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        assert!(turtle.end_fill(&mut drawing).is_err());
+    }
+
+    #[test]
+    fn pen_size_must_be_positive() {
+        let mut turtle = Turtle::new();
+        assert!(turtle.set_pen_size(-1f64).is_err());
+        assert!(turtle.set_pen_size(2.5).is_ok());
+        assert_eq!(turtle.pen_size, 2.5);
+    }
+
+    #[test]
+    fn moves_are_traced() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.line = Some(4);
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        turtle.pen_down();
+        let _ = turtle.turn(90f64);
+        let _ = turtle.move_turtle(5f64, &mut drawing);
+        let events = turtle.trace.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1],
+            TraceEvent {
+                kind: EventKind::Move,
+                start: Location::new(0f64, 10f64),
+                end: Location::new(5f64, 10f64),
+                colour: 7f32,
+                mode: Mode::PenDown,
+                heading: 90f64,
+                line: Some(4),
+            }
+        );
+        assert_eq!(events[0].mode, Mode::PenUp);
+    }
+
+    #[test]
+    fn arc_is_traced_from_start_to_end() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.arc(90f64, 10f64, &mut drawing);
+        let event = &turtle.trace.events()[0];
+        assert_eq!(event.kind, EventKind::Arc);
+        assert_eq!(event.start, Location::new(0f64, 10f64));
+        assert_eq!(event.end, Location::new(10f64, 0f64));
+    }
+
+    #[test]
+    fn pen_colour_out_of_palette() {
+        let mut turtle = Turtle::new();
+        assert!(turtle.set_pen_colour(15f32).is_ok());
+        assert!(turtle.set_pen_colour(16f32).is_err());
+        assert!(turtle.set_pen_colour(0.5).is_err());
+        assert_eq!(turtle.colour, 15f32);
+    }
+
+    #[test]
+    fn sideways_moves_keep_heading() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.move_sideways(-5f64, &mut drawing);
+        assert_eq!(turtle.position, Location::new(-5f64, 0f64));
+        let mut turtle = Turtle::with_convention(AngleConvention::Math);
+        let _ = turtle.move_sideways(5f64, &mut drawing);
+        assert_eq!(turtle.position, Location::new(0f64, -5f64));
+        assert_eq!(turtle.heading(), 0f64);
+    }
+
+    #[test]
+    fn hidden_turtle_has_no_sprite() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        assert_eq!(turtle.pose().sprite(), None);
+        turtle.show_turtle(&mut drawing);
+        turtle.hide_turtle(&mut drawing);
+        assert_eq!(turtle.pose().sprite(), None);
+        assert!(!drawing.poses(0)[0].1.visible);
+    }
+
+    #[test]
+    fn sprite_at_final_position() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::with_convention(AngleConvention::Math);
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        turtle.change_colour(4f32);
+        turtle.show_turtle(&mut drawing);
+        assert_eq!(
+            turtle.pose().sprite(),
+            Some(DrawCommand::turtle_sprite(
+                &Location::new(10f64, 0f64),
+                90f64,
+                COLORS[4]
+            ))
+        );
+    }
+
+    #[test]
+    fn pop_restores_pushed_state() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.push_state();
+        turtle.pen_down();
+        let _ = turtle.turn(45f64);
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        turtle.change_colour(3f32);
+        let _ = turtle.set_pen_size(4f64);
+        let res = turtle.pop_state();
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::origin());
+        assert_eq!(turtle.angle, 0f64);
+        assert_eq!(turtle.colour, 7f32);
+        assert_eq!(turtle.mode, Mode::PenUp);
+        assert_eq!(turtle.pen_size, 1f64);
+        // Jumping back draws nothing.
+        assert_eq!(drawing.commands().len(), 1);
+    }
+
+    #[test]
+    fn states_pop_in_reverse() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.push_state();
+        let _ = turtle.set_x(5f64, &mut drawing);
+        turtle.push_state();
+        let _ = turtle.set_x(9f64, &mut drawing);
+        let _ = turtle.pop_state();
+        assert_eq!(turtle.position, Location::new(5f64, 0f64));
+        let _ = turtle.pop_state();
+        assert_eq!(turtle.position, Location::origin());
+    }
+
+    #[test]
+    fn pop_empty_stack() {
+        let mut turtle = Turtle::new();
+        assert!(turtle.pop_state().is_err());
+    }
+
+    #[test]
+    fn lsystem_draws_and_turns_left() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.pen_down();
+        let res = turtle.run_lsystem("F+F", 10f64, 90f64, &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(-10f64, 10f64));
+        assert_eq!(drawing.commands().len(), 2);
+    }
+
+    #[test]
+    fn lsystem_branches_and_skips() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.pen_down();
+        let res = turtle.run_lsystem("[-F]fX", 10f64, 90f64, &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(0f64, 10f64));
+        assert_eq!(turtle.mode, Mode::PenDown);
+        // Only the branch is drawn.
+        assert_eq!(drawing.commands().len(), 1);
+    }
+
+    #[test]
+    fn lsystem_unbalanced_pop() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        assert!(turtle
+            .run_lsystem("F]", 10f64, 90f64, &mut drawing)
+            .is_err());
+    }
+
+    #[test]
+    fn set_pos_moves_to_point() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_pos(3f64, -4f64, &mut drawing);
+        assert_eq!(turtle.position, Location::new(3f64, -4f64));
+        assert_eq!(turtle.pos(), vec![3f64, -4f64]);
+    }
+
+    #[test]
+    fn home_resets_position_and_heading() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::with_convention(AngleConvention::Math);
+        let _ = turtle.set_pos(3f64, -4f64, &mut drawing);
+        let _ = turtle.set_heading(45f64);
+        assert!(turtle.home(&mut drawing).is_ok());
+        assert_eq!(turtle.position, Location::origin());
+        assert_eq!(turtle.heading(), 0f64);
+    }
+
+    #[test]
+    fn towards_in_both_conventions() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_pos(1f64, 1f64, &mut drawing);
+        assert_eq!(turtle.towards(&Location::new(1f64, -5f64)), 180f64);
+        assert_eq!(turtle.towards(&Location::new(0f64, 1f64)), 270f64);
+        let mut turtle = Turtle::with_convention(AngleConvention::Math);
+        let _ = turtle.set_pos(1f64, 1f64, &mut drawing);
+        assert_eq!(turtle.towards(&Location::new(1f64, -5f64)), 270f64);
+        assert_eq!(turtle.towards(&Location::new(0f64, 1f64)), 180f64);
+    }
+
+    #[test]
+    fn towards_own_position_keeps_heading() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_pos(1f64, 1f64, &mut drawing);
+        let _ = turtle.set_heading(135f64);
+        assert_eq!(turtle.towards(&Location::new(1f64, 1f64)), 135f64);
+    }
+
+    #[test]
+    fn distance_to_point() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_pos(1f64, 1f64, &mut drawing);
+        assert_eq!(turtle.distance(&Location::new(4f64, 5f64)), 5f64);
+    }
+
+    #[test]
+    fn pen_down_set_pos_draws() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.pen_down();
+        let _ = turtle.set_x(10f64, &mut drawing);
+        let _ = turtle.set_y(-5f64, &mut drawing);
+        assert_eq!(
+            drawing.commands()[1],
+            DrawCommand::Line {
+                start: Location::new(10f64, 0f64),
+                end: Location::new(10f64, -5f64),
+                style: turtle.style(),
+            }
+        );
+        assert_eq!(drawing.commands().len(), 2);
+    }
+
+    #[test]
+    fn teleport_set_pos_draws_nothing() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.teleport = true;
+        turtle.pen_down();
+        let res = turtle.set_pos(10f64, 10f64, &mut drawing);
+        assert!(res.is_ok());
+        assert_eq!(turtle.position, Location::new(10f64, 10f64));
+        assert!(drawing.is_empty());
+    }
+
+    #[test]
+    fn teleport_set_pos_is_traced_and_filled() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.teleport = true;
+        turtle.begin_fill();
+        let _ = turtle.set_pos(10f64, 0f64, &mut drawing);
+        let _ = turtle.set_pos(10f64, 10f64, &mut drawing);
+        let events = turtle.trace.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].kind, EventKind::Move);
+        assert_eq!(events[1].start, Location::new(10f64, 0f64));
+        assert_eq!(events[1].end, Location::new(10f64, 10f64));
+        assert!(turtle.end_fill(&mut drawing).is_ok());
+        assert_eq!(
+            drawing.commands(),
+            &[DrawCommand::Fill {
+                points: vec![
+                    Location::origin(),
+                    Location::new(10f64, 0f64),
+                    Location::new(10f64, 10f64),
+                ],
+                colour: COLORS[turtle.colour as usize],
+            }]
+        );
+    }
+
+    #[test]
+    fn set_pos_stops_at_fence() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.set_bounds(32f64, 32f64);
+        turtle.set_boundary(Boundary::Fence);
+        assert!(turtle.set_x(40f64, &mut drawing).is_err());
+    }
+
+    #[test]
+    fn erase_and_reverse_draw_with_their_ink() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        turtle.pen_erase();
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        assert_eq!(turtle.pen_mode(), "ERASE");
+        turtle.pen_reverse();
+        turtle.arc(90f64, 5f64, &mut drawing);
+        assert_eq!(turtle.pen_mode(), "REVERSE");
+        match &drawing.commands()[0] {
+            DrawCommand::Line { style, .. } => assert_eq!(style.ink, Ink::Erase),
+            _ => panic!("expected a line"),
+        }
+        match &drawing.commands()[1] {
+            DrawCommand::Arc { style, .. } => assert_eq!(style.ink, Ink::Reverse),
+            _ => panic!("expected an arc"),
+        }
+    }
+
+    #[test]
+    fn pen_mode_paint() {
+        let mut turtle = Turtle::new();
+        assert_eq!(turtle.pen_mode(), "PAINT");
+        turtle.pen_down();
+        assert_eq!(turtle.pen_mode(), "PAINT");
+    }
+
+    #[test]
+    fn pen_styles() {
+        let mut turtle = Turtle::new();
+        assert_eq!(turtle.set_pen_style("dash"), Ok(()));
+        assert_eq!(turtle.dash, vec![8f64, 4f64]);
+        assert_eq!(turtle.set_pen_style("DOT"), Ok(()));
+        assert_eq!(turtle.dash, vec![1f64, 3f64]);
+        assert_eq!(turtle.set_pen_style("5 2 1 2"), Ok(()));
+        assert_eq!(turtle.dash, vec![5f64, 2f64, 1f64, 2f64]);
+        assert_eq!(turtle.set_pen_style("3"), Ok(()));
+        assert_eq!(turtle.dash, vec![3f64, 3f64]);
+        assert_eq!(turtle.set_pen_style("solid"), Ok(()));
+        assert!(turtle.dash.is_empty());
+        assert!(turtle.set_pen_style("wiggly").is_err());
+        assert!(turtle.set_pen_style("4 -2").is_err());
+        assert!(turtle.set_pen_style("0 0").is_err());
+        assert!(turtle.dash.is_empty());
+    }
+
+    #[test]
+    fn dashes_carry_on_between_moves() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_pen_style("4 4");
+        turtle.pen_down();
+        let _ = turtle.move_turtle(6f64, &mut drawing);
+        let _ = turtle.move_turtle(5f64, &mut drawing);
+        turtle.pen_up();
+        let _ = turtle.move_turtle(5f64, &mut drawing);
+        turtle.pen_down();
+        let _ = turtle.move_turtle(5f64, &mut drawing);
+        let offsets: Vec<f64> = drawing
+            .commands()
+            .iter()
+            .map(|command| match command {
+                DrawCommand::Line { style, .. } => style.dash.as_ref().unwrap().offset,
+                _ => panic!("expected a line"),
+            })
+            .collect();
+        assert_eq!(offsets, vec![0f64, 6f64, 0f64]);
+    }
+
+    #[test]
+    fn odd_dashes_carry_on_between_moves() {
+        let mut drawing = DisplayList::new();
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_pen_style("4 2 1");
+        turtle.pen_down();
+        let _ = turtle.move_turtle(9f64, &mut drawing);
+        let _ = turtle.move_turtle(9f64, &mut drawing);
+        let _ = turtle.move_turtle(9f64, &mut drawing);
+        // The pattern only comes round again after 14, with 9 into it a gap of 2 rather
+        // than the dash of 4 that 9 % 7 would start on.
+        let dashes: Vec<Dash> = drawing
+            .commands()
+            .iter()
+            .map(|command| match command {
+                DrawCommand::Line { style, .. } => style.dash.clone().unwrap(),
+                _ => panic!("expected a line"),
+            })
+            .collect();
+        assert_eq!(dashes[0].pattern, vec![4f64, 2f64, 1f64, 4f64, 2f64, 1f64]);
+        let offsets: Vec<f64> = dashes.iter().map(|dash| dash.offset).collect();
+        assert_eq!(offsets, vec![0f64, 9f64, 4f64]);
+    }
+
+    #[test]
+    fn pop_state_restores_pen_style() {
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_pen_style("dot");
+        turtle.push_state();
+        let _ = turtle.set_pen_style("solid");
+        let _ = turtle.pop_state();
+        assert_eq!(turtle.dash, vec![1f64, 3f64]);
+    }
+
+    #[test]
+    fn scaled_dashes_carry_on_between_moves() {
+        let mut drawing = DisplayList::new();
+        let _ = drawing.set_scale(1.5);
+        let mut turtle = Turtle::new();
+        let _ = turtle.set_pen_style("4 4");
+        turtle.pen_down();
+        let _ = turtle.move_turtle(10f64, &mut drawing);
+        let _ = turtle.move_turtle(5f64, &mut drawing);
+        // On the canvas the pattern is 6 6 and the first line 15 long, so the second
+        // starts 3 into the pattern.
+        match &drawing.commands()[1] {
+            DrawCommand::Line { style, .. } => {
+                let dash = style.dash.as_ref().unwrap();
+                assert_eq!(dash.pattern, vec![6f64, 6f64]);
+                assert_eq!(dash.offset, 3f64);
+            }
+            _ => panic!("expected a line"),
+        }
     }
 }
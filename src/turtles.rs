@@ -0,0 +1,179 @@
+use crate::display::DisplayList;
+use crate::turtle::Turtle;
+
+/// The name of the turtle every program starts with.
+pub const FIRST_TURTLE: &str = "turtle";
+
+/// Every turtle in a program, drawing on the same canvas, and which one commands go to.
+#[derive(Debug)]
+pub struct Turtles {
+    /// In the order they were made.
+    turtles: Vec<(String, Turtle)>,
+    current: usize,
+}
+
+impl Turtles {
+    /// Starts with `first` as the only turtle. New turtles are set up like it.
+    pub fn new(first: Turtle) -> Self {
+        Turtles {
+            turtles: vec![(String::from(FIRST_TURTLE), first)],
+            current: 0,
+        }
+    }
+}
+
+impl Turtles {
+    /// The turtle commands and queries like XCOR go to.
+    pub fn current(&self) -> &Turtle {
+        &self.turtles[self.current].1
+    }
+
+    pub fn current_mut(&mut self) -> &mut Turtle {
+        &mut self.turtles[self.current].1
+    }
+
+    pub fn current_name(&self) -> &str {
+        &self.turtles[self.current].0
+    }
+
+    /// Makes a turtle at the origin with the same convention, edges and visibility as the
+    /// first one. Commands keep going to the current turtle until TELL.
+    pub fn new_turtle(&mut self, name: &str) -> Result<(), String> {
+        if self.index(name).is_some() {
+            return Err(format!("There is already a turtle called {name}"));
+        }
+        let first = &self.turtles[0].1;
+        let mut turtle = Turtle::with_convention(first.convention);
+        turtle.boundary = first.boundary;
+        turtle.bounds = first.bounds;
+        turtle.visible = first.visible;
+        turtle.teleport = first.teleport;
+        turtle.id = self.turtles.len();
+        self.turtles.push((String::from(name), turtle));
+        Ok(())
+    }
+
+    /// Sends commands to the turtle called `name` from now on.
+    pub fn tell(&mut self, name: &str) -> Result<(), String> {
+        let index = self
+            .index(name)
+            .ok_or_else(|| format!("There is no turtle called {name}"))?;
+        self.switch_to(index);
+        Ok(())
+    }
+
+    /// Runs `commands` with the turtle called `name`, then goes back to the current one.
+    pub fn ask<T>(
+        &mut self,
+        name: &str,
+        commands: impl FnOnce(&mut Turtle) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let previous = self.current;
+        self.tell(name)?;
+        let result = commands(self.current_mut());
+        self.switch_to(previous);
+        result
+    }
+
+    /// Notes where every turtle finished, and paints those that are showing over the
+    /// drawing.
+    pub fn show(&self, drawing: &mut DisplayList) {
+        for (_, turtle) in &self.turtles {
+            turtle.record_pose(drawing);
+        }
+        drawing.show_turtles();
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.turtles.iter().position(|(other, _)| other == name)
+    }
+
+    /// The trace moves with the current turtle so it stays one list in the order things
+    /// happened, whichever turtle did them.
+    fn switch_to(&mut self, index: usize) {
+        let trace = std::mem::take(&mut self.turtles[self.current].1.trace);
+        self.current = index;
+        self.turtles[self.current].1.trace = trace;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+    use crate::trace::{EventKind, TraceEvent};
+    use crate::turtle::AngleConvention;
+
+    #[test]
+    fn starts_with_one_turtle() {
+        let turtles = Turtles::new(Turtle::new());
+        assert_eq!(turtles.current_name(), FIRST_TURTLE);
+    }
+
+    #[test]
+    fn tell_switches_turtle() {
+        let mut turtles = Turtles::new(Turtle::new());
+        assert!(turtles.new_turtle("bob").is_ok());
+        turtles.current_mut().position = Location::new(5.0, 5.0);
+        assert!(turtles.tell("bob").is_ok());
+        assert_eq!(turtles.current_name(), "bob");
+        assert_eq!(turtles.current().position, Location::origin());
+    }
+
+    #[test]
+    fn new_turtle_copies_setup() {
+        let mut first = Turtle::with_convention(AngleConvention::Math);
+        first.set_bounds(10.0, 20.0);
+        let mut turtles = Turtles::new(first);
+        let _ = turtles.new_turtle("bob");
+        let _ = turtles.tell("bob");
+        assert_eq!(turtles.current().convention, AngleConvention::Math);
+        assert_eq!(turtles.current().bounds, Some((10.0, 20.0)));
+    }
+
+    #[test]
+    fn names_must_be_unique_and_known() {
+        let mut turtles = Turtles::new(Turtle::new());
+        assert!(turtles.new_turtle(FIRST_TURTLE).is_err());
+        assert!(turtles.tell("nobody").is_err());
+        assert!(turtles.ask("nobody", |_| Ok(())).is_err());
+        assert_eq!(turtles.current_name(), FIRST_TURTLE);
+    }
+
+    #[test]
+    fn ask_comes_back() {
+        let mut turtles = Turtles::new(Turtle::new());
+        let _ = turtles.new_turtle("bob");
+        let x = turtles.ask("bob", |turtle| {
+            turtle.position = Location::new(3.0, 0.0);
+            Ok(turtle.position.x())
+        });
+        assert_eq!(x, Ok(3.0));
+        assert_eq!(turtles.current_name(), FIRST_TURTLE);
+        assert_eq!(turtles.current().position, Location::origin());
+    }
+
+    #[test]
+    fn trace_follows_current_turtle() {
+        let mut turtles = Turtles::new(Turtle::new());
+        let _ = turtles.new_turtle("bob");
+        let _ = turtles.ask("bob", |turtle| {
+            turtle.visible = true;
+            turtle.trace.push(TraceEvent {
+                kind: EventKind::Label,
+                start: Location::origin(),
+                end: Location::origin(),
+                colour: turtle.colour,
+                mode: turtle.mode,
+                heading: turtle.heading(),
+                line: None,
+            });
+            Ok(())
+        });
+        assert_eq!(turtles.current().trace.events().len(), 1);
+        let mut drawing = DisplayList::new();
+        turtles.show(&mut drawing);
+        assert_eq!(drawing.sprites().len(), 1);
+        assert_eq!(drawing.poses(0).len(), 2);
+    }
+}